@@ -1,16 +1,191 @@
 from __future__ import annotations
+import datetime
 import enum
 import typing
 
+@typing.final
+class ActivatedInterface:
+    """A COM interface activated on a device, for use with ctypes or comtypes"""
+
+    address: int
+    name: str
+
 @typing.final
 class AudioDevice:
+    channel_count: int
+    container_id: str | None
+    data_flow: DataFlow
     device_id: str
+    endpoint_guid: str | None
+    enhancements_enabled: bool
+    enumerator_name: str | None
     events: AudioDeviceEventIterator
+    exclusive_mode_allowed: bool | None
+    id_guid: str | None
+    id_prefix: str | None
     name: str
+    physical_speakers: list[str]
+    property_events: DeviceNotificationsIterator
+    supports_event_driven_mode: bool | None
+    volume_limits: tuple[float, float] | None
+
+    def activate(self, /, interface: str) -> ActivatedInterface:
+        """Activate a COM interface on this device that this library doesn't wrap itself
+
+        Only these interfaces are supported: IAudioEndpointVolume, IAudioMeterInformation,
+        IAudioSessionManager2 and IAudioClient."""
+    def active_session_count(self, /) -> int:
+        """Number of application audio sessions that are currently active on this device"""
+    def aggregate_peak(self, /) -> float:
+        """The loudest peak level (0.0 to 1.0) of any application session on this device
+
+        This is the level the applications are producing, before the endpoint volume and mute are
+        applied -- compare with ``open_meter().peak_value()`` which measures the endpoint itself."""
+    def clear_volume_limits(self, /):
+        """Remove the limits set by ``set_volume_limits``"""
+    def default_roles(self, /) -> list[Role]:
+        """The roles for which this device is currently the default (among devices of its data flow)"""
+    def get_property(self, /, fmtid: str, pid: int) -> int | bool | str | datetime.datetime | None:
+        """Read a value from the device's property store
+
+        `fmtid` is the property key's GUID, as a ``{XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX}`` string.
+        Integer, boolean, string, GUID (as a string) and date values are supported. None is
+        returned if the device doesn't have the property."""
+    def is_input(self, /) -> bool:
+        """Whether this is an input (CAPTURE) device"""
+    def is_output(self, /) -> bool:
+        """Whether this is an output (RENDER) device"""
+    def is_playing(self, /, threshold: float | None = None) -> bool:
+        """Whether audio is currently playing on (or recording from) this device
+
+        By default this checks whether any application's audio session on the device is active.
+        Sessions can be active while only playing silence, so if `threshold` is given the device's
+        peak meter is checked against it instead, e.g. ``is_playing(threshold=0.01)``."""
+    def level_poller(self, /) -> LevelPoller:
+        """Open this device's volume control and peak meter together, to be polled for levels"""
+    def loudest_session(self, /) -> AudioSession | None:
+        """The active application audio session on this device with the highest peak level right
+        now, i.e. what is making the most noise, or None if no session is active"""
+    def matches_event(self, /, event: DeviceCollectionEvent) -> bool:
+        """Whether a DeviceCollectionEvent is about this device
 
+        The ids are compared after ``normalize_device_id``, so differences of case or surrounding
+        whitespace don't matter."""
+    def mix_format(self, /) -> MixFormat:
+        """The sample format the audio engine mixes this device's shared-mode streams in"""
+    def mix_format_events(self, /, interval_ms: int = 1000) -> MixFormatEventsIterator:
+        """Asynchronously yield the device's new ``(sample_rate, channels, bits_per_sample)`` each
+        time its mix format changes
+
+        Windows doesn't notify changes of format (such as a driver switching from 44.1 to 48 kHz),
+        so the format is read every `interval_ms` milliseconds and compared with the last one seen."""
+    def mute_for(self, /, duration_ms: int) -> typing.Awaitable[None]:
+        """Mute this device for `duration_ms` milliseconds, then put back the mute state it had before
+
+        The previous state is also put back if the returned awaitable is cancelled."""
+    def open_meter(self, /, hold_ms: int | None = None) -> Meter:
+        """Open the peak meter of this device, to be polled for levels
+
+        With `hold_ms`, the meter's ``levels()`` also reports a peak-hold value: the highest recent
+        peak, held for that long and then falling away over the same time again."""
+    def sessions(self, /) -> list[AudioSession]:
+        """The application audio sessions (of any state) on this device"""
     def set_default(self, /, role: Role):
         """Make this device the default for the specified role"""
+    def set_default_all_roles(self, /) -> list[tuple[str, bool, typing.Optional[str]]]:
+        """Make this device the default for every role (console, multimedia and communications)
+
+        A role that can't be set doesn't stop the others. Returns a ``(role, ok, error_message)``
+        tuple for each role, where `role` is the name of the Role."""
+    def set_enhancements_enabled(self, /, enabled: bool):
+        """Turn the device's audio enhancements on or off
+
+        Windows only allows this for administrators; otherwise PermissionError is raised."""
+    def set_mute(self, /, muted: bool, context: str | None = None):
+        """Mute or unmute the device
+
+        `context` is a GUID string that the resulting VolumeChangeEvent carries in its ``context``
+        attribute, so a listener can tell which part of an application made the change. Without
+        it, the change is tagged with ``LIBRARY_CONTEXT``."""
+    def set_volume_limits(self, /, min: float, max: float):
+        """Keep volume changes made through this ``AudioDevice`` object between `min` and `max`
+
+        The limits are advisory: they apply to ``volume()``'s setters and steps and to
+        ``with_volume``, but other applications, the volume keys and other ``AudioDevice`` objects
+        for the same device can still go past them."""
+    def state_events(self, /) -> DeviceNotificationsIterator:
+        """Asynchronously yield this device's new DeviceState each time it changes (e.g. to UNPLUGGED
+        or DISABLED), without the rest of the collection's events
+
+        The events are STATE_CHANGED DeviceCollectionEvents, with the new state in ``state``."""
+    def supports_metering(self, /) -> bool:
+        """Whether this device's peak meter can be read
+
+        Some virtual endpoints don't support metering, in which case ``open_meter()`` or polling
+        the meter raises; this lets a UI leave the meter out instead."""
+    def to_token(self, /) -> dict[str, str]:
+        """A small, JSON-serializable reference to this device, to find it again later
+
+        See ``DeviceCollection.from_token``"""
     def toggle_mute(self, /): ...
+    def volume(self, /) -> EndpointVolume:
+        """Open the volume control of this device, for several volume operations in a row"""
+    def volume_events(
+        self,
+        /,
+        debounce_ms: int | None = None,
+        emit_initial: bool = False,
+        policy: SendPolicy | None = None,
+        dedupe: bool = False,
+        master_only: bool = False,
+        unit: str = 'scalar',
+        min_interval_ms: int = 0,
+        with_db: bool = False,
+    ) -> AudioDeviceEventIterator:
+        """Asyncronoysly yield the volume change events for this device, with options
+
+        With `debounce_ms`, a burst of changes (such as from turning a volume wheel) is coalesced
+        into a single event with the final value, once there have been no changes for that long.
+
+        With `emit_initial`, the device's current volume and mute state is yielded as the first
+        event, before anything has changed.
+
+        `policy` decides what happens to a change when the previous one hasn't been consumed yet:
+        BLOCK (the default) delivers every event in order, while real-time consumers that only care about the
+        latest state can use DROP_OLDEST.
+
+        With `dedupe`, an event with the same mute, volume and channel volumes as the one
+        immediately before it is dropped, as Windows sometimes notifies the same change more than
+        once.
+
+        With `master_only`, events only carry the master volume and mute: ``channel_volumes`` is
+        left empty (so ``channel_count`` is 0 and ``balance`` None), which saves copying the channel
+        volumes of every change on devices with many channels.
+
+        `unit` is either "scalar" (0.0 to 1.0) or "db", in which case each event's ``volume`` is the
+        level in dB that the endpoint reports for it, the same as its ``volume_db``. If the device
+        can't report that level for a change, reading that event raises OSError.
+
+        With `min_interval_ms`, at most one change is passed on per that many milliseconds: changes
+        arriving sooner are coalesced into the latest, which follows once the interval is up. This
+        protects against drivers that notify extremely rapidly while a slider is dragged. Unlike
+        `debounce_ms`, changes keep coming during a burst.
+
+        With `with_db`, each event's ``volume_db`` is filled in with the level the endpoint reports
+        for it, which costs a call to the device per event; otherwise it is None."""
+    def wait_for_volume(
+        self, /, predicate: typing.Callable[[VolumeChangeEvent], bool], timeout_ms: int
+    ) -> typing.Awaitable[typing.Optional[VolumeChangeEvent]]:
+        """Wait for a volume change of this device for which `predicate(event)` is true
+
+        Resolves to the first matching VolumeChangeEvent, or None if there wasn't one within
+        `timeout_ms` milliseconds. This uses a listener of its own, so any ``events`` or
+        ``volume_events`` iterator on this device keeps receiving changes while it waits."""
+    def with_volume(self, /, level: float) -> ScopedVolume:
+        """Set this device's volume for the duration of an ``async with`` block
+
+        ``async with device.with_volume(0.5):`` sets the volume to `level` (0.0 to 1.0) on entering
+        the block and puts back the volume it had before on leaving it, even if the block raises."""
 
 @typing.final
 class AudioDeviceEventIterator:
@@ -18,15 +193,48 @@ class AudioDeviceEventIterator:
 
     device: typing.Any
 
+    def drain(self, /) -> list[VolumeChangeEvent]:
+        """Return all the events that are already waiting, without waiting for more"""
+    def stop(self, /):
+        """Stop listening for changes to the device, ending the iteration"""
+    def try_next(self, /) -> VolumeChangeEvent | None:
+        """Return the next event if one is already waiting, otherwise None, without waiting"""
     def __aiter__(self, /): ...
     def __anext__(self, /): ...
 
+@typing.final
+class AudioSession:
+    """An application's stream playing to or recording from a device"""
+
+    device: AudioDevice
+    device_id: str
+
+    def is_active(self, /) -> bool:
+        """Whether the session is currently active (has an open, running stream)"""
+    def peak_value(self, /) -> float:
+        """Peak sample value of this session alone, in the range 0.0 to 1.0"""
+
+@typing.final
+class CollectionCallback:
+    """Registration of a callback for changes to devices in a collection"""
+
+    def close(self, /):
+        """Stop calling the callback"""
+    def stop(self, /):
+        """Stop calling the callback, the same as ``close()``"""
+
 @typing.final
 class CollectionEventsIterator:
     """Async iterator of changes to devices in a collection"""
 
+    closed: bool
+
     def close(self, /):
         """Close the iterator"""
+    def drain(self, /) -> list[DeviceCollectionEvent]:
+        """Return all the events that are already waiting, without waiting for more"""
+    def try_next(self, /) -> DeviceCollectionEvent | None:
+        """Return the next event if one is already waiting, otherwise None, without waiting"""
     def __aiter__(self, /): ...
     def __anext__(self, /): ...
 
@@ -36,23 +244,279 @@ class DataFlow:
     CAPTURE = ...
     RENDER = ...
 
+@typing.final
+class DefaultChangeEventsIterator:
+    """Async iterator of the devices that become the default"""
+
+    def close(self, /):
+        """Close the iterator"""
+    def __aiter__(self, /): ...
+    def __anext__(self, /): ...
+
+@typing.final
+class DefaultOutputEventsIterator:
+    """Async iterator of the volume changes of the default output device, whichever device that is"""
+
+    def close(self, /):
+        """Close the iterator"""
+    def __aiter__(self, /): ...
+    def __anext__(self, /): ...
+
+@typing.final
+class DefaultSummary:
+    """Snapshot of the default output and input devices and their volume"""
+
+    input: VolumeChangeEvent | None
+    output: VolumeChangeEvent | None
+
 @typing.final
 class DeviceCollection:
+    default_output_events: DefaultOutputEventsIterator
     devices: dict[str, AudioDevice]
+    ducking_preference: DuckingPreference
     events: CollectionEventsIterator
+    input_devices: dict[str, AudioDevice]
+    output_devices: dict[str, AudioDevice]
+
+    def all_devices(self, /, state_mask: DeviceState = None) -> list[AudioDevice]:
+        """Get the devices of both data flows (input and output) in a single list"""
+    def close_all(self, /):
+        """Stop every event iterator and callback created through this collection
+
+        This covers the collection's own event iterators and callbacks, and the volume, property and
+        state events of devices obtained from it, which all end as though they had been closed. It is
+        called when leaving a ``with collection:`` block."""
+    def collection_events(
+        self,
+        /,
+        resolve_defaults: bool = False,
+        dedupe: bool = False,
+        background_thread: bool = False,
+        include_metadata: bool = False,
+    ) -> CollectionEventsIterator:
+        """Asyncronoysly yield the events for this collection, with options
+
+        With `resolve_defaults`, DEFAULT_CHANGED events carry the new default ``AudioDevice`` in
+        their ``device`` attribute, saving a lookup by id.
+
+        With `dedupe`, an event identical to the one immediately before it is dropped, as Windows
+        sometimes notifies the same change more than once.
+
+        Windows calls the library from its own threads, and by default the events are handed on
+        to this iterator by tasks on the async-std executor, whose small pool of threads is shared
+        with the library's other background work. With `background_thread`, a dedicated OS thread
+        does that instead, so events aren't held up while that pool is busy (e.g. with callbacks
+        waiting for the GIL).
+
+        With `include_metadata`, events carry the device's friendly name in their ``name``
+        attribute, and ADDED events its ``state``. Names are remembered, so REMOVED events are
+        labelled too even though the device can no longer be looked up."""
+    def current_default_token(self, /, dataflow: DataFlow, role: Role) -> str | None:
+        """Get an opaque token for the current default device of the given data flow and role
+
+        The token changes whenever the default device does, so it can be polled and compared to
+        spot changes without subscribing to ``events``. It is None if there is no such device.
+        The data flow must be RENDER or CAPTURE; ALL raises ValueError."""
+    def default_change_events(
+        self, /, dataflow: DataFlow | None = None, role: Role | None = None
+    ) -> DefaultChangeEventsIterator:
+        """Asynchronously yield the new default ``AudioDevice`` each time the default changes
+
+        Only changes for `dataflow` and `role` are yielded, where given; otherwise changes for
+        every data flow and role are. Added, removed and state change events are skipped."""
+    def default_output_channels(self, /) -> tuple[int, list[str]] | None:
+        """The channel count and physical speaker positions of the current default output device
+
+        Returns ``(channel_count, physical_speakers)``, as the ``AudioDevice`` properties of
+        those names, or None if there is no output device."""
+    def default_output_step_down(self, /):
+        """Lower the volume of whichever device is currently the default output by one step
+
+        This uses the device's own step size, the same as the keyboard volume keys, and follows
+        changes of default."""
+    def default_output_step_up(self, /):
+        """Raise the volume of whichever device is currently the default output by one step
+
+        This uses the device's own step size, the same as the keyboard volume keys, and follows
+        changes of default."""
+    def default_output_volume_events(self, /) -> DefaultOutputEventsIterator:
+        """Asynchronously yield the volume events of whichever device is the default output
+
+        The same as the ``default_output_events`` property, for use as
+        ``async for event in collection.default_output_volume_events():``."""
+    def default_summary(self, /) -> DefaultSummary:
+        """Get the default output and input devices along with their current volume and mute, in one
+        call"""
+    def device_for_event(self, /, event: DeviceCollectionEvent) -> AudioDevice:
+        """Get the device a DeviceCollectionEvent is about, e.g. to start listening to the volume of
+        a newly added device
 
+        Raises KeyError if the device no longer exists."""
+    def device_for_session(self, /, session: AudioSession) -> AudioDevice:
+        """Get the device an AudioSession plays to or records from, looked up afresh by its id
+
+        Raises KeyError if the device no longer exists."""
+    def devices_capturing(self, /) -> list[AudioDevice]:
+        """Get the input devices that something is currently recording from
+
+        These are the active input devices with at least one active application audio session,
+        e.g. for a "microphone in use" indicator."""
+    def devices_in_container(self, /, container_id: str, state_mask: DeviceState = None) -> list[AudioDevice]:
+        """Get the devices that are part of the physical device with the given container id"""
+    def devices_ordered(self, /, dataflow: DataFlow) -> list[AudioDevice]:
+        """Get the active devices of the given data flow, for showing in a device picker
+
+        The current default device (for the console role) comes first, then the rest in order of
+        name."""
+    def diff(self, /, previous_ids: list[str]) -> tuple[list[AudioDevice], list[str]]:
+        """Compare the current devices with the ids from an earlier enumeration, such as
+        ``list(collection.devices.keys())``
+
+        Returns ``(added, removed)``: the devices (of any state) that aren't in `previous_ids`, and
+        the ids from `previous_ids` whose devices no longer exist. Ids are compared after
+        ``normalize_device_id``."""
     def filter_devices(self, /, dataflow: DataFlow, state_mask: DeviceState = None) -> FilteredDeviceCollection:
         """Get a collection of devices matching the given parameters"""
+    def from_token(self, /, token: dict[str, str]) -> AudioDevice:
+        """Find the device referred to by a token from ``AudioDevice.to_token``
+
+        The device is looked up by id first. If that device is no longer active, an active device
+        with the same name (and data flow) is returned instead."""
+    def get_default_device(self, /, dataflow: DataFlow, role: Role) -> AudioDevice:
+        """Get the current default device of the given data flow for the given role
+
+        The data flow must be RENDER or CAPTURE; ALL raises ValueError."""
     def get_default_input_device(self, /) -> AudioDevice:
         """Get the current default input device (aka microphone)"""
+    def get_default_multimedia_input_device(self, /) -> AudioDevice:
+        """Get the current default input device for the multimedia role (e.g. voice recording)"""
+    def get_default_multimedia_output_device(self, /) -> AudioDevice:
+        """Get the current default output device for the multimedia role (music, movies etc)"""
     def get_default_output_device(self, /) -> AudioDevice:
         """Get the current default output device (aka speakers)"""
+    def get_device_by_name(self, /, name: str, dataflow: DataFlow | None = None) -> AudioDevice:
+        """Get the active device with the given friendly name
+
+        Names are compared exactly. Raises KeyError if no active device of `dataflow` (by default
+        either) has the name, and ValueError if more than one does, as then only the device id can
+        tell them apart (the error lists their ids). Use ``get_devices_by_name`` to get them all."""
+    def get_devices_by_name(self, /, name: str, dataflow: DataFlow | None = None) -> list[AudioDevice]:
+        """Get every active device with the given friendly name, such as two identical USB headsets
+
+        Names are compared exactly, and the devices are sorted by id, so the order is the same
+        from one call to the next. The list is empty if no active device of `dataflow` (by default
+        either) has the name."""
+    def list_devices_json(self, /, dataflow: DataFlow | None = None) -> list[dict[str, typing.Union[str, bool]]]:
+        """List the devices of `dataflow` as plain dicts, e.g. to show in a table or pass to
+        another process
+
+        Every device of `dataflow` (by default either) is included whatever its state. Each dict
+        has exactly these keys, holding only strings and booleans so the list can be passed
+        straight to ``json.dumps``:
+
+        - ``id``: the device id
+        - ``name``: the friendly name
+        - ``state``: ``"ACTIVE"``, ``"DISABLED"``, ``"NOT_PRESENT"`` or ``"UNPLUGGED"``
+        - ``data_flow``: ``"RENDER"`` or ``"CAPTURE"``
+        - ``default_console``, ``default_multimedia``, ``default_communications``: whether the
+        device is the default of its data flow for that role"""
+    def mute_all(self, /, dataflow: DataFlow, muted: bool) -> list[tuple[str, bool, typing.Optional[str]]]:
+        """Mute (or unmute) every active device of the given data flow
+
+        A device that can't be muted doesn't stop the others being muted. Returns a
+        ``(device_id, ok, error_message)`` tuple for each device."""
+    def mute_all_inputs(self, /, muted: bool = True) -> list[tuple[str, bool, typing.Optional[str]]]:
+        """Mute (or unmute) every active input device (microphone)
+
+        A device that can't be muted doesn't stop the others being muted. Returns a
+        ``(device_id, ok, error_message)`` tuple for each device, as ``mute_all`` does."""
+    def mute_schedule(self, /, device_ids: list[str], duration_ms: int) -> list[tuple[str, bool, typing.Optional[str]]]:
+        """Mute the devices with the given ids, then put back the mute state each had before after
+        `duration_ms` milliseconds
+
+        This returns straight away: the mute states are put back by a background task, which doesn't
+        need this collection or anything else to keep running. A device that can't be found or muted
+        doesn't stop the others being muted. Returns a ``(device_id, ok, error_message)`` tuple for
+        each device."""
+    def output_volume_snapshot(self, /) -> dict[str, tuple[float, bool]]:
+        """The master volume and mute state of every active output device"""
+    def problem_devices(self, /) -> list[tuple[AudioDevice, DeviceState]]:
+        """Get the devices that aren't working, for troubleshooting: those that are disabled, not
+        present or unplugged
+
+        Returns ``(device, state)`` pairs, covering both data flows."""
+    def register_collection_callback(self, /, callback: typing.Callable) -> CollectionCallback:
+        """Call `callback` with each event for this collection, without needing an asyncio loop
+
+        The callback is invoked from a background thread with a single DeviceCollectionEvent
+        argument. It stays registered until the returned object is closed or garbage collected."""
+    def set_default_by_id(self, /, device_id: str, role: Role):
+        """Make the device with the given id the default for the specified role"""
+    def set_default_devices(
+        self, /, output_id: str | None = None, input_id: str | None = None
+    ) -> list[tuple[str, bool, typing.Optional[str]]]:
+        """Make the devices with the given ids the default output and/or input device, for every role
+
+        A device or role that can't be made the default doesn't stop the others. Returns a
+        ``(target, ok, error_message)`` tuple for each device and role, where `target` is the
+        device id and role name, like ``"{0.0.0.00000000}.{...} CONSOLE"``."""
+    def set_defaults_by_name(self, /, output_name: str | None = None, input_name: str | None = None):
+        """Make the output and/or input devices with the given friendly names the default for every
+        role
+
+        Both names are looked up (as by ``get_device_by_name``) before either default is changed,
+        so a missing or ambiguous name leaves the defaults as they were."""
+    def snapshot_state(self, /) -> SystemAudioState:
+        """Get the state of the whole system's audio in one call: the default output and input
+        devices with their volume, the output's peak level, and how many devices are active
+
+        Missing default devices are reported as None rather than raising."""
+    def toggle_container_mute(self, /, container_id: str) -> bool:
+        """Toggle the mute of every active endpoint of a physical device, such as a headset's
+        speakers and microphone together
+
+        The endpoints are those ``devices_in_container`` finds for `container_id`. Which way to
+        toggle is decided by the output endpoint (or the first endpoint, if there is no output):
+        if it is muted everything is unmuted, otherwise everything is muted. Raises KeyError if no
+        active endpoint is in the container. Returns the new mute state.
+
+        An endpoint that can't be toggled doesn't stop the others. If any failed, the first error
+        is raised once the rest have been toggled, and the container is left partly toggled."""
+    def toggle_default_input_mute(self, /) -> bool:
+        """Toggle the mute of whichever device is currently the default input (aka microphone)
+
+        The default device is looked up on every call, so this follows changes of default.
+        Returns the new mute state."""
+    def toggle_default_output_mute(self, /) -> bool:
+        """Toggle the mute of whichever device is currently the default output
+
+        The default device is looked up on every call, so this follows changes of default.
+        Returns the new mute state."""
+    def watch(
+        self,
+        /,
+        on_event: typing.Callable[[DeviceCollectionEvent], typing.Any],
+        on_error: typing.Callable[[Exception], typing.Any] | None = None,
+    ) -> CollectionCallback:
+        """Watch this collection in the background, calling `on_event` with each
+        DeviceCollectionEvent
+
+        This is the same as ``register_collection_callback``, with somewhere to send errors: an
+        exception raised by `on_event`, or an error reading an event, is passed to `on_error` and
+        the watcher carries on with the next event. Without `on_error`, exceptions are printed and
+        errors reading an event are logged as warnings. Call ``stop()`` on the returned object to
+        stop watching."""
+    def __enter__(self, /) -> DeviceCollection: ...
+    def __exit__(self, /, exc_type, exc_value, traceback) -> bool: ...
 
 @typing.final
 class DeviceCollectionEvent:
     dataflow: DataFlow | None
+    device: AudioDevice | None
     device_id: str
     kind: DeviceCollectionEventType
+    name: str | None
+    property_key: tuple[str, int] | None
     role: Role | None
     state: DeviceState | None
 
@@ -60,9 +524,19 @@ class DeviceCollectionEvent:
 class DeviceCollectionEventType:
     ADDED = ...
     DEFAULT_CHANGED = ...
+    PROPERTY_VALUE_CHANGED = ...
     REMOVED = ...
     STATE_CHANGED = ...
 
+@typing.final
+class DeviceNotificationsIterator:
+    """Async iterator of one kind of collection event, for a single device"""
+
+    def close(self, /):
+        """Close the iterator"""
+    def __aiter__(self, /): ...
+    def __anext__(self, /): ...
+
 @typing.final
 class DeviceState(enum.IntFlag):
     ACTIVE = ...
@@ -70,20 +544,144 @@ class DeviceState(enum.IntFlag):
     NOT_PRESENT = ...
     UNPLUGGED = ...
 
+@typing.final
+class DuckingPreference:
+    """What Windows does to other sounds when it detects communications activity"""
+
+    DO_NOTHING = ...
+    MUTE_OTHERS = ...
+    REDUCE_50 = ...
+    REDUCE_80 = ...
+
+@typing.final
+class EndpointVolume:
+    """Volume control of an audio device"""
+
+    def channel_count(self, /) -> int:
+        """Number of channels the device has"""
+    def get(self, /) -> float:
+        """The master volume, in the range 0.0 to 1.0"""
+    def get_mute(self, /) -> bool:
+        """Whether the device is muted"""
+    def range(self, /) -> tuple[float, float, float]:
+        """The device's volume range in dB, as ``(min_db, max_db, step_db)``"""
+    def set(self, /, volume: float):
+        """Set the master volume, in the range 0.0 to 1.0"""
+    def set_mute(self, /, muted: bool):
+        """Mute or unmute the device"""
+    def step_down(self, /):
+        """Lower the volume by one step of the device's own step size"""
+    def step_up(self, /):
+        """Raise the volume by one step of the device's own step size"""
+
 @typing.final
 class FilteredDeviceCollection:
     def __getitem__(self, key, /): ...
     def __len__(self, /): ...
 
+@typing.final
+class LevelPoller:
+    """Volume control and peak meter of an audio device, for polling both in a loop"""
+
+    def poll(self, /) -> tuple[float, bool, float]:
+        """Read the master volume, mute state and peak level as ``(volume, mute, peak)``
+
+        The GIL is released while Windows is asked, so other Python threads keep running while
+        one polls the device in a tight loop."""
+
+@typing.final
+class Meter:
+    """Peak meter of an audio device"""
+
+    def channel_peaks(self, /) -> tuple[float, ...]:
+        """Peak sample value of each channel, in the range 0.0 to 1.0"""
+    def levels(self, /) -> tuple[float, float]:
+        """The current peak value and the peak-hold value, as ``(peak, held)``
+
+        The held value only updates when this is called, so poll it at the display's frame rate.
+        Without ``hold_ms`` on ``open_meter()``, the held value is just the current peak."""
+    def peak_value(self, /) -> float:
+        """Peak sample value across all channels, in the range 0.0 to 1.0"""
+
+@typing.final
+class MixFormat:
+    """The sample format of a device's shared-mode mix"""
+
+    bits_per_sample: int
+    channel_mask: int
+    channels: int
+    sample_format: str
+    sample_rate: int
+    valid_bits_per_sample: int
+
+    def to_float32(self, /, data: bytes) -> bytes:
+        """Convert raw audio in this format to float samples in the range -1.0 to 1.0
+
+        The samples are returned packed as native-endian float32, ready for ``array('f', ...)`` or
+        ``numpy.frombuffer(..., dtype=numpy.float32)``. Channels stay interleaved, as they are in
+        `data`. Raises ValueError if the format isn't one that can be converted, or `data` isn't a
+        whole number of samples."""
+
+@typing.final
+class MixFormatEventsIterator:
+    """Async iterator of changes to a device's mix format, found by polling"""
+
+    def __aiter__(self, /): ...
+    def __anext__(self, /): ...
+
 @typing.final
 class Role:
     COMMS = ...
     CONSOLE = ...
     MULTIMEDIA = ...
 
+@typing.final
+class ScopedVolume:
+    """Async context manager that sets a device's volume for the duration of a block"""
+
+    def __aenter__(self, /) -> typing.Awaitable[AudioDevice]: ...
+    def __aexit__(self, /, exc_type, exc_value, traceback) -> typing.Awaitable[bool]: ...
+
+@typing.final
+class SendPolicy:
+    """What to do with a notification when the consumer hasn't caught up with the previous ones"""
+
+    BLOCK = ...
+    DROP_NEWEST = ...
+    DROP_OLDEST = ...
+
+@typing.final
+class SystemAudioState:
+    """Snapshot of the whole system's audio state, for a status display or health check"""
+
+    active_inputs: int
+    active_outputs: int
+    input: VolumeChangeEvent | None
+    output: VolumeChangeEvent | None
+    output_peak: float | None
+
+    def to_dict(self, /) -> dict[str, typing.Any]:
+        """The snapshot as a plain dict, with the devices' states as ``VolumeChangeEvent.to_dict``"""
+
 @typing.final
 class VolumeChangeEvent:
+    balance: float | None
+    channel_count: int
     channel_volumes: tuple[float, ...]
+    context: str | None
+    data_flow: DataFlow
     device: AudioDevice
     mute: bool
     volume: float
+    volume_db: float | None
+
+    def to_dict(self, /) -> dict[str, typing.Any]:
+        """The event as a plain dict, e.g. for logging or serializing"""
+
+LIBRARY_CONTEXT: str
+
+def normalize_device_id(device_id: str) -> str:
+    """Put a device id in the form Windows itself gives ids in
+
+    This removes surrounding whitespace and lower-cases the letters. Lookups by id already do this,
+    so use it to compare ids from elsewhere (e.g. config files) against ``AudioDevice.device_id``."""
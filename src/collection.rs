@@ -1,38 +1,71 @@
 use std::sync::Arc;
 
 use anyhow::Context;
-use async_std::task;
 use log::debug;
 
 use async_std::channel::Sender;
 use windows::{
-    core::{implement, AgileReference, Result, PCWSTR},
+    core::{implement, AgileReference, AsImpl, Result},
     Win32::{
         Media::Audio::{
-            eConsole, IMMDeviceCollection, IMMDeviceEnumerator, IMMNotificationClient,
+            IMMDeviceCollection, IMMDeviceEnumerator, IMMNotificationClient,
             IMMNotificationClient_Impl, MMDeviceEnumerator,
         },
         System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER},
+        UI::Shell::PropertiesSystem::PROPERTYKEY,
     },
 };
 
-use crate::{com, device::AudioDevice};
+use crate::{
+    com, device::AudioDevice, drain::DrainSender, listeners::Listeners, ELEMENT_NOT_FOUND,
+};
 
 use super::device;
 use super::enums;
 use super::errors::WindowsAudioError;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum DeviceNotificationEvent {
     StateChanged(String, enums::DeviceState),
     Added(String),
     Removed(String),
     DefaultChanged(String, enums::DataFlow, enums::Role),
+    PropertyValueChanged(String, PROPERTYKEY),
+}
+
+impl DeviceNotificationEvent {
+    pub fn device_id(&self) -> &str {
+        match self {
+            DeviceNotificationEvent::StateChanged(device_id, _)
+            | DeviceNotificationEvent::Added(device_id)
+            | DeviceNotificationEvent::Removed(device_id)
+            | DeviceNotificationEvent::DefaultChanged(device_id, _, _)
+            | DeviceNotificationEvent::PropertyValueChanged(device_id, _) => device_id,
+        }
+    }
+
+    /// The id of the new default device, if this is a change of default for `dataflow` and
+    /// `role` (None matching any)
+    pub fn default_changed_to(
+        &self,
+        dataflow: Option<enums::DataFlow>,
+        role: Option<enums::Role>,
+    ) -> Option<&str> {
+        match self {
+            DeviceNotificationEvent::DefaultChanged(device_id, flow, event_role)
+                if (dataflow.is_none() || dataflow == Some(*flow))
+                    && (role.is_none() || role == Some(*event_role)) =>
+            {
+                Some(device_id)
+            }
+            _ => None,
+        }
+    }
 }
 
 #[implement(IMMNotificationClient)]
 pub struct NotificationClient {
-    channel: Sender<anyhow::Result<DeviceNotificationEvent>>,
+    channel: DrainSender<anyhow::Result<DeviceNotificationEvent>>,
 }
 
 impl NotificationClient {
@@ -40,10 +73,22 @@ impl NotificationClient {
     pub fn new(
         rx: Sender<anyhow::Result<DeviceNotificationEvent>>,
     ) -> anyhow::Result<IMMNotificationClient> {
-        let val = NotificationClient { channel: rx };
+        Self::with_sender(DrainSender::new(rx))
+    }
+
+    /// Notification client sending its events through `channel`
+    pub fn with_sender(
+        channel: DrainSender<anyhow::Result<DeviceNotificationEvent>>,
+    ) -> anyhow::Result<IMMNotificationClient> {
+        let val = NotificationClient { channel };
 
         Ok(val.into())
     }
+
+    /// End the event stream of `client`, which must have been created by `NotificationClient::new`
+    pub fn close(client: &IMMNotificationClient) {
+        client.as_impl().channel.close();
+    }
 }
 
 impl IMMNotificationClient_Impl for NotificationClient {
@@ -65,34 +110,23 @@ impl IMMNotificationClient_Impl for NotificationClient {
         let msg = process(deviceid, dwnewstate)
             .context("Failed to convert OnDeviceStateChange to expected types");
 
-        let channel = self.channel.clone();
-        task::spawn(async move {
-            _ = channel.send(msg).await;
-        });
+        self.channel.send(msg);
         Ok(())
     }
 
     fn OnDeviceAdded(&self, win_device_id: &windows::core::PCWSTR) -> Result<()> {
         let device_id = unsafe { win_device_id.to_string()? };
 
-        let channel = self.channel.clone();
-        task::spawn(async move {
-            _ = channel
-                .send(Ok(DeviceNotificationEvent::Added(device_id)))
-                .await;
-        });
+        self.channel
+            .send(Ok(DeviceNotificationEvent::Added(device_id)));
         Ok(())
     }
 
     fn OnDeviceRemoved(&self, win_device_id: &windows::core::PCWSTR) -> Result<()> {
         let device_id = unsafe { win_device_id.to_string()? };
 
-        let channel = self.channel.clone();
-        task::spawn(async move {
-            _ = channel
-                .send(Ok(DeviceNotificationEvent::Removed(device_id)))
-                .await;
-        });
+        self.channel
+            .send(Ok(DeviceNotificationEvent::Removed(device_id)));
         Ok(())
     }
 
@@ -119,23 +153,26 @@ impl IMMNotificationClient_Impl for NotificationClient {
         let msg = process(device_id, &flow, &role)
             .context("Failed to convert OnDefaultDeviceChanged to expected types");
 
-        let channel = self.channel.clone();
-        task::spawn(async move {
-            _ = channel.send(msg).await;
-        });
+        self.channel.send(msg);
         Ok(())
     }
 
     fn OnPropertyValueChanged(
         &self,
-        _pwstrdeviceid: &windows::core::PCWSTR,
-        _key: &windows::Win32::UI::Shell::PropertiesSystem::PROPERTYKEY,
+        win_device_id: &windows::core::PCWSTR,
+        key: &windows::Win32::UI::Shell::PropertiesSystem::PROPERTYKEY,
     ) -> Result<()> {
+        let device_id = unsafe { win_device_id.to_string()? };
+
+        self.channel
+            .send(Ok(DeviceNotificationEvent::PropertyValueChanged(
+                device_id, *key,
+            )));
         Ok(())
     }
 }
 
-pub struct DeviceCollection(Arc<IMMDeviceCollection>);
+pub struct DeviceCollection(Arc<IMMDeviceCollection>, DeviceEnumerator);
 
 // This feels like a bad idea, but AgileReference doesn't work for IMMDeviceCollection
 unsafe impl Send for DeviceCollection {}
@@ -148,11 +185,15 @@ impl DeviceCollection {
 
     pub fn get(&self, idx: u32) -> anyhow::Result<device::AudioDevice> {
         let device = unsafe { self.0.Item(idx)? };
-        device::AudioDevice::new(device)
+        Ok(device::AudioDevice::new(device)?.tracked_by(&self.1))
     }
 }
 
-pub struct DeviceEnumerator(AgileReference<IMMDeviceEnumerator>);
+/// The system's device enumerator, along with every listener registered through it
+///
+/// Clones share the enumerator and its listeners.
+#[derive(Clone)]
+pub struct DeviceEnumerator(AgileReference<IMMDeviceEnumerator>, Arc<Listeners>);
 
 impl DeviceEnumerator {
     pub fn new() -> Result<Self> {
@@ -163,20 +204,17 @@ impl DeviceEnumerator {
             device_enumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_INPROC_SERVER)?;
         }
 
-        Ok(DeviceEnumerator(AgileReference::new(&device_enumerator)?))
+        Ok(DeviceEnumerator(
+            AgileReference::new(&device_enumerator)?,
+            Arc::default(),
+        ))
     }
 
     pub fn get_device(&self, device_id: &str) -> anyhow::Result<AudioDevice> {
         match self.0.resolve() {
             Ok(enumerator) => {
-                let mut text = device_id.encode_utf16().collect::<Vec<_>>();
-                text.push(0);
-                let wstr = PCWSTR::from_raw(text.as_ptr());
-
-                match unsafe { enumerator.GetDevice(wstr) } {
-                    Ok(device) => device::AudioDevice::new(device),
-                    Err(e) => Err(WindowsAudioError::from(e).into()),
-                }
+                let device_id = device::normalize_device_id(device_id);
+                Ok(device::AudioDevice::from_id(&enumerator, &device_id)?.tracked_by(self))
             }
             Err(e) => Err(WindowsAudioError::from(e).into()),
         }
@@ -193,7 +231,7 @@ impl DeviceEnumerator {
                     unsafe { enumerator.EnumAudioEndpoints(dataflow.into(), state_mask.into()) }
                         .context("unable to get collection")?;
 
-                Ok(DeviceCollection(Arc::new(collection)))
+                Ok(DeviceCollection(Arc::new(collection), self.clone()))
             }
             Err(e) => Err(WindowsAudioError::from(e).into()),
         }
@@ -202,27 +240,90 @@ impl DeviceEnumerator {
     pub fn get_default_device(
         &self,
         dataflow: windows::Win32::Media::Audio::EDataFlow,
+        role: windows::Win32::Media::Audio::ERole,
     ) -> anyhow::Result<device::AudioDevice> {
         match self.0.resolve() {
             Ok(enumerator) => {
-                let device = unsafe { enumerator.GetDefaultAudioEndpoint(dataflow, eConsole)? };
-                device::AudioDevice::new(device)
+                let device = unsafe { enumerator.GetDefaultAudioEndpoint(dataflow, role)? };
+                Ok(device::AudioDevice::new(device)?.tracked_by(self))
             }
             Err(e) => Err(WindowsAudioError::from(e).into()),
         }
     }
 
+    /// The id of the current default device, or None if there is no device for that flow and role
+    ///
+    /// This is cheaper than `get_default_device` as it doesn't read any of the device properties.
+    pub fn get_default_device_id(
+        &self,
+        dataflow: windows::Win32::Media::Audio::EDataFlow,
+        role: windows::Win32::Media::Audio::ERole,
+    ) -> anyhow::Result<Option<String>> {
+        let enumerator = self.0.resolve().map_err(WindowsAudioError::from)?;
+        match unsafe { enumerator.GetDefaultAudioEndpoint(dataflow, role) } {
+            Ok(device) => Ok(Some(unsafe {
+                device
+                    .GetId()?
+                    .to_string()
+                    .map_err(WindowsAudioError::from)?
+            })),
+            Err(e) if e.code() == ELEMENT_NOT_FOUND => Ok(None),
+            Err(e) => Err(WindowsAudioError::from(e).into()),
+        }
+    }
+
+    /// The listeners registered through this enumerator
+    pub fn listeners(&self) -> &Arc<Listeners> {
+        &self.1
+    }
+
     pub fn register_notification(&self, client: &IMMNotificationClient) -> anyhow::Result<()> {
         debug!("Registering notification client {:?}", client);
         let enumerator = self.0.resolve()?;
         unsafe { enumerator.RegisterEndpointNotificationCallback(client)? };
+        self.1
+            .notifications
+            .insert(client, AgileReference::new(client)?);
         Ok(())
     }
 
     pub fn unregister_notification(&self, client: &IMMNotificationClient) -> anyhow::Result<()> {
+        if !self.1.notifications.remove(client) {
+            // Already unregistered by `close_all`
+            return Ok(());
+        }
         debug!("Unregistering notification client {:?}", client);
         let enumerator = self.0.resolve()?;
         unsafe { enumerator.UnregisterEndpointNotificationCallback(client)? };
         Ok(())
     }
+
+    /// Unregister every notification client registered through this enumerator, and every volume
+    /// listener on the devices it created, ending their event streams
+    ///
+    /// All of them are closed even if some fail; the first error is returned.
+    pub fn close_all(&self) -> anyhow::Result<()> {
+        let enumerator = self.0.resolve()?;
+        let mut result = Ok(());
+
+        for client in self.1.notifications.take_all() {
+            let closed = client.and_then(|client| unsafe {
+                enumerator.UnregisterEndpointNotificationCallback(&client)?;
+                NotificationClient::close(&client);
+                Ok(())
+            });
+            if let Err(e) = closed {
+                debug!("Unable to unregister notification client: {}", e);
+                result = result.and(Err(e));
+            }
+        }
+        for callback in self.1.volume.take_all() {
+            if let Err(e) = callback.and_then(|callback| device::close_volume_callback(&callback)) {
+                debug!("Unable to unregister volume listener: {}", e);
+                result = result.and(Err(e));
+            }
+        }
+
+        Ok(result.map_err(WindowsAudioError::from)?)
+    }
 }
@@ -1,34 +1,151 @@
-use anyhow::Context;
+use anyhow::{bail, Context};
 use async_std::task;
 use log::debug;
 
-use async_std::channel::Sender;
 use windows::{
-    core::{implement, AgileReference, AsImpl, Result, PCWSTR},
+    core::{implement, AgileReference, AsImpl, Error, IUnknown, Interface, Result, GUID, PCWSTR},
     Win32::{
-        Devices::FunctionDiscovery::PKEY_Device_FriendlyName,
+        Devices::FunctionDiscovery::{
+            PKEY_Device_ContainerId, PKEY_Device_EnumeratorName, PKEY_Device_FriendlyName,
+        },
         Media::Audio::{
             ERole,
             Endpoints::{
                 IAudioEndpointVolume, IAudioEndpointVolumeCallback,
-                IAudioEndpointVolumeCallback_Impl,
+                IAudioEndpointVolumeCallback_Impl, IAudioMeterInformation,
             },
-            IMMDevice,
+            IAudioClient, IAudioSessionManager2, IMMDevice, IMMDeviceEnumerator, IMMEndpoint,
+            PKEY_AudioEndpoint_Disable_SysFx, PKEY_AudioEndpoint_GUID,
+            PKEY_AudioEndpoint_PhysicalSpeakers, PKEY_AudioEndpoint_Supports_EventDriven_Mode,
+            AUDIO_VOLUME_NOTIFICATION_DATA, ENDPOINT_SYSFX_DISABLED, ENDPOINT_SYSFX_ENABLED,
+        },
+        Media::KernelStreaming::{
+            SPEAKER_BACK_CENTER, SPEAKER_BACK_LEFT, SPEAKER_BACK_RIGHT, SPEAKER_FRONT_CENTER,
+            SPEAKER_FRONT_LEFT, SPEAKER_FRONT_LEFT_OF_CENTER, SPEAKER_FRONT_RIGHT,
+            SPEAKER_FRONT_RIGHT_OF_CENTER, SPEAKER_LOW_FREQUENCY, SPEAKER_SIDE_LEFT,
+            SPEAKER_SIDE_RIGHT, SPEAKER_TOP_BACK_CENTER, SPEAKER_TOP_BACK_LEFT,
+            SPEAKER_TOP_BACK_RIGHT, SPEAKER_TOP_CENTER, SPEAKER_TOP_FRONT_CENTER,
+            SPEAKER_TOP_FRONT_LEFT, SPEAKER_TOP_FRONT_RIGHT,
         },
         System::Com::{CoCreateInstance, CLSCTX_ALL, STGM_READ},
+        UI::Shell::PropertiesSystem::PROPERTYKEY,
     },
 };
 
-use crate::policy_config::{IPolicyConfig, PolicyConfig};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 
-// use super::enums;
+use crate::collection::DeviceEnumerator;
+use crate::com;
+use crate::drain::{DrainSender, Throttle, Throttled};
+use crate::format::{self, MixFormat};
+use crate::listeners::Listeners;
+use crate::policy_config::{IPolicyConfig, IPolicyConfigVista, PolicyConfig, PolicyConfigVista};
+use crate::property::{parse_guid, read_property, write_uint_property, PropertyValue};
+use crate::session::{self, AudioSession};
+
+use super::enums;
 use super::errors::WindowsAudioError;
 
+/// The event context of the volume and mute changes this library makes, unless given another
+///
+/// Windows passes it on to volume listeners, so they can recognise changes made through here.
+pub const LIBRARY_CONTEXT: GUID = GUID::from_u128(0x6f1c2a4e_3b7d_4e58_9a0c_5d2e8b71c3f4);
+
+/// The "Allow applications to take exclusive control of this device" setting, which the SDK
+/// headers don't name
+const PKEY_EXCLUSIVE_MODE_ALLOWED: PROPERTYKEY = PROPERTYKEY {
+    fmtid: GUID::from_u128(0xb3f8fa53_0004_438e_9003_51a46e139bfc),
+    pid: 3,
+};
+
+/// Names of the `SPEAKER_*` channel-mask bits, in channel order
+const SPEAKER_POSITIONS: [(u32, &str); 18] = [
+    (SPEAKER_FRONT_LEFT, "FrontLeft"),
+    (SPEAKER_FRONT_RIGHT, "FrontRight"),
+    (SPEAKER_FRONT_CENTER, "FrontCenter"),
+    (SPEAKER_LOW_FREQUENCY, "LFE"),
+    (SPEAKER_BACK_LEFT, "BackLeft"),
+    (SPEAKER_BACK_RIGHT, "BackRight"),
+    (SPEAKER_FRONT_LEFT_OF_CENTER, "FrontLeftOfCenter"),
+    (SPEAKER_FRONT_RIGHT_OF_CENTER, "FrontRightOfCenter"),
+    (SPEAKER_BACK_CENTER, "BackCenter"),
+    (SPEAKER_SIDE_LEFT, "SideLeft"),
+    (SPEAKER_SIDE_RIGHT, "SideRight"),
+    (SPEAKER_TOP_CENTER, "TopCenter"),
+    (SPEAKER_TOP_FRONT_LEFT, "TopFrontLeft"),
+    (SPEAKER_TOP_FRONT_CENTER, "TopFrontCenter"),
+    (SPEAKER_TOP_FRONT_RIGHT, "TopFrontRight"),
+    (SPEAKER_TOP_BACK_LEFT, "TopBackLeft"),
+    (SPEAKER_TOP_BACK_CENTER, "TopBackCenter"),
+    (SPEAKER_TOP_BACK_RIGHT, "TopBackRight"),
+];
+
+pub fn speaker_positions(mask: u32) -> Vec<&'static str> {
+    SPEAKER_POSITIONS
+        .iter()
+        .filter(|(bit, _)| mask & bit != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+/// Put a device id in the form Windows itself gives ids in, so it can be looked up or compared
+///
+/// Surrounding whitespace is removed and ASCII letters are lower-cased, as ids from `GetId` (like
+/// `{0.0.0.00000000}.{b3f8fa53-0004-438e-9003-51a46e139bfc}`) are all lower case.
+pub fn normalize_device_id(device_id: &str) -> String {
+    device_id.trim().to_ascii_lowercase()
+}
+
+/// Split an endpoint id like `{0.0.0.00000000}.{b3f8fa53-0004-438e-9003-51a46e139bfc}` into its
+/// flow prefix (`{0.0.0.00000000}` for render, `{0.0.1.00000000}` for capture) and its GUID
+///
+/// Returns None if the id isn't in that form.
+pub fn split_device_id(device_id: &str) -> Option<(&str, &str)> {
+    let (prefix, guid) = device_id.split_once("}.")?;
+    let prefix = &device_id[..prefix.len() + 1];
+
+    let valid_prefix = prefix.starts_with('{')
+        && prefix[1..prefix.len() - 1]
+            .chars()
+            .all(|c| c == '.' || c.is_ascii_hexdigit());
+    if !valid_prefix || !guid.starts_with('{') || parse_guid(guid).is_err() {
+        return None;
+    }
+    Some((prefix, guid))
+}
+
+/// Left/right balance of a stereo device, from -1.0 (fully left) to 1.0 (fully right)
+///
+/// None if the device doesn't have exactly two channels.
+pub fn stereo_balance(channel_volumes: &[f32]) -> Option<f32> {
+    match *channel_volumes {
+        [left, right] if left == right => Some(0.0),
+        [left, right] => Some((right - left) / left.max(right)),
+        _ => None,
+    }
+}
+
+/// The range of master volume levels, in dB, that an endpoint supports
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VolumeRange {
+    pub min_db: f32,
+    pub max_db: f32,
+    pub step_db: f32,
+}
+
 pub struct AudioDevice {
     pub id: String,
     pub friendly_name: String,
-    device: IMMDevice,
-    volume_listener: Option<AgileReference<IAudioEndpointVolumeCallback>>,
+    // Held as an agile reference so the device can be handed between threads (and so Python can
+    // use it from any thread), resolving it in whichever apartment it's used from
+    device: AgileReference<IMMDevice>,
+    // A device's data flow never changes, so it is only asked for once
+    data_flow: OnceLock<enums::DataFlow>,
+    volume_limits: Option<VolumeLimits>,
+    volume_listener: Option<VolumeListener>,
+    // The enumerator the device came from, if any, which its listeners are registered through
+    enumerator: Option<DeviceEnumerator>,
 }
 
 impl AudioDevice {
@@ -54,56 +171,445 @@ impl AudioDevice {
         anyhow::Ok(AudioDevice {
             id,
             friendly_name,
-            device,
+            device: AgileReference::new(&device)?,
+            data_flow: OnceLock::new(),
+            volume_limits: None,
             volume_listener: None,
+            enumerator: None,
         })
     }
 
-    pub fn toggle_mute(&self) -> Result<()> {
+    /// The device's interface, for the apartment of the calling thread
+    fn device(&self) -> Result<IMMDevice> {
+        self.device.resolve()
+    }
+
+    /// Record the volume listeners of this device with those of `enumerator`, so they can be closed
+    /// with the rest, and remember it for registering notifications about this device
+    pub fn tracked_by(mut self, enumerator: &DeviceEnumerator) -> Self {
+        self.enumerator = Some(enumerator.clone());
+        self
+    }
+
+    /// The enumerator this device came from, if it came from one
+    pub fn enumerator(&self) -> Option<&DeviceEnumerator> {
+        self.enumerator.as_ref()
+    }
+
+    /// Look up the device with the given endpoint id string
+    pub fn from_id(enumerator: &IMMDeviceEnumerator, device_id: &str) -> anyhow::Result<Self> {
+        let mut text = device_id.encode_utf16().collect::<Vec<_>>();
+        text.push(0);
+        let wstr = PCWSTR::from_raw(text.as_ptr());
+
+        let device = unsafe { enumerator.GetDevice(wstr) }.map_err(WindowsAudioError::from)?;
+        Self::new(device)
+    }
+
+    pub fn data_flow(&self) -> anyhow::Result<enums::DataFlow> {
+        if let Some(flow) = self.data_flow.get() {
+            return Ok(*flow);
+        }
+        let flow = unsafe { self.device()?.cast::<IMMEndpoint>()?.GetDataFlow()? };
+        let flow = enums::DataFlow::try_from(flow.0)?;
+        Ok(*self.data_flow.get_or_init(|| flow))
+    }
+
+    pub fn state(&self) -> anyhow::Result<enums::DeviceState> {
+        Ok(unsafe { self.device()?.GetState()? }.into())
+    }
+
+    /// Flip the mute state of this device, returning the new state
+    pub fn toggle_mute(&self) -> Result<bool> {
+        unsafe {
+            let endpoint: IAudioEndpointVolume = self.device()?.Activate(CLSCTX_ALL, None)?;
+            let muted = !endpoint.GetMute()?.as_bool();
+            endpoint.SetMute(muted, &LIBRARY_CONTEXT)?;
+            Ok(muted)
+        }
+    }
+
+    /// Raise (or lower) the volume by one step of the endpoint's own step size, as the volume
+    /// keys do
+    pub fn volume_step(&self, up: bool) -> Result<()> {
+        self.endpoint_volume()?.step(up)
+    }
+
+    pub fn set_mute(&self, muted: bool) -> Result<()> {
+        self.set_mute_with_context(muted, None)
+    }
+
+    /// Set the mute state, tagging the change with `context` (by default `LIBRARY_CONTEXT`), which
+    /// the resulting `VolumeChangeEvent` carries so listeners can tell who made it
+    pub fn set_mute_with_context(&self, muted: bool, context: Option<&GUID>) -> Result<()> {
+        let context = context.unwrap_or(&LIBRARY_CONTEXT);
         unsafe {
-            let endpoint: IAudioEndpointVolume = self.device.Activate(CLSCTX_ALL, None)?;
-            let current = endpoint.GetMute()?.as_bool();
-            endpoint.SetMute(!current, std::ptr::null())?;
+            let endpoint: IAudioEndpointVolume = self.device()?.Activate(CLSCTX_ALL, None)?;
+            endpoint.SetMute(muted, context)?;
         };
         Ok(())
     }
 
-    pub fn register_volume_change(&mut self, channel: Sender<VolumeChangeEvent>) -> Result<()> {
-        let vcallback = VolumeCallbackClient::new(&self.device, channel)?;
+    /// Read the current volume state, in the same shape as a change notification
+    pub fn volume_state(&self) -> Result<VolumeChangeEvent> {
+        unsafe {
+            let endpoint: IAudioEndpointVolume = self.device()?.Activate(CLSCTX_ALL, None)?;
+            let channel_volumes = (0..endpoint.GetChannelCount()?)
+                .map(|channel| endpoint.GetChannelVolumeLevelScalar(channel))
+                .collect::<Result<_>>()?;
+
+            Ok(VolumeChangeEvent {
+                mute: endpoint.GetMute()?.as_bool(),
+                volume: endpoint.GetMasterVolumeLevelScalar()?,
+                volume_db: endpoint.GetMasterVolumeLevel().ok(),
+                channel_volumes,
+                context: None,
+            })
+        }
+    }
+
+    /// Number of channels the endpoint's volume control has
+    pub fn channel_count(&self) -> Result<u32> {
+        self.endpoint_volume()?.channel_count()
+    }
+
+    /// The master volume (0.0 to 1.0) and mute state, without the per-channel detail of
+    /// `volume_state`
+    pub fn master_volume(&self) -> Result<(f32, bool)> {
+        unsafe {
+            let endpoint: IAudioEndpointVolume = self.device()?.Activate(CLSCTX_ALL, None)?;
+            Ok((
+                endpoint.GetMasterVolumeLevelScalar()?,
+                endpoint.GetMute()?.as_bool(),
+            ))
+        }
+    }
+
+    pub fn mix_format(&self) -> Result<MixFormat> {
+        format::mix_format(&self.device()?)
+    }
+
+    pub fn sessions(&self) -> Result<Vec<AudioSession>> {
+        session::sessions(&self.device()?)
+    }
+
+    /// The active session with the highest peak value right now, if any session is active
+    pub fn loudest_session(&self) -> Result<Option<AudioSession>> {
+        let mut loudest: Option<(f32, AudioSession)> = None;
+        for session in self.sessions()? {
+            if !session.is_active()? {
+                continue;
+            }
+            let peak = session.peak_value()?;
+            let louder = match &loudest {
+                Some((max, _)) => peak > *max,
+                None => true,
+            };
+            if louder {
+                loudest = Some((peak, session));
+            }
+        }
+        Ok(loudest.map(|(_, session)| session))
+    }
+
+    pub fn active_session_count(&self) -> Result<usize> {
+        session::active_session_count(&self.device()?)
+    }
+
+    /// The loudest peak value across all the sessions on this device
+    ///
+    /// Unlike the endpoint meter this still reports the level of sessions that are playing while
+    /// the endpoint itself is muted.
+    pub fn aggregate_peak(&self) -> Result<f32> {
+        self.sessions()?.iter().try_fold(0.0, |peak, session| {
+            Ok(f32::max(peak, session.peak_value()?))
+        })
+    }
+
+    /// Whether the device's peak meter can be read
+    ///
+    /// Metering is usually done in software, so this tries it rather than relying on
+    /// `QueryHardwareSupport`, which only reports meters in the hardware.
+    pub fn supports_metering(&self) -> bool {
+        self.open_meter()
+            .and_then(|meter| meter.peak_value())
+            .is_ok()
+    }
+
+    /// Activate the volume control and peak meter once, for reading them together repeatedly
+    pub fn level_poller(&self) -> Result<LevelPoller> {
+        let device = self.device()?;
+        let (volume, meter): (IAudioEndpointVolume, IAudioMeterInformation) = unsafe {
+            (
+                device.Activate(CLSCTX_ALL, None)?,
+                device.Activate(CLSCTX_ALL, None)?,
+            )
+        };
+        Ok(LevelPoller {
+            volume: AgileReference::new(&volume)?,
+            meter: AgileReference::new(&meter)?,
+        })
+    }
+
+    pub fn endpoint_volume(&self) -> Result<EndpointVolume> {
+        let endpoint: IAudioEndpointVolume = unsafe { self.device()?.Activate(CLSCTX_ALL, None)? };
+        Ok(EndpointVolume(endpoint, self.volume_limits))
+    }
+
+    pub fn volume_limits(&self) -> Option<VolumeLimits> {
+        self.volume_limits
+    }
+
+    /// Set (or with None, remove) the limits that volume changes made through this object's
+    /// `EndpointVolume`s are kept within
+    pub fn set_volume_limits(&mut self, limits: Option<VolumeLimits>) {
+        self.volume_limits = limits;
+    }
+
+    pub fn open_meter(&self) -> Result<AudioMeter> {
+        let meter: IAudioMeterInformation = unsafe { self.device()?.Activate(CLSCTX_ALL, None)? };
+        Ok(AudioMeter(meter))
+    }
+
+    /// Activate one of a known set of interfaces on the device, by interface name
+    pub fn activate(&self, interface: &str) -> anyhow::Result<IUnknown> {
+        unsafe {
+            Ok(match interface {
+                "IAudioEndpointVolume" => self
+                    .device()?
+                    .Activate::<IAudioEndpointVolume>(CLSCTX_ALL, None)?
+                    .cast()?,
+                "IAudioMeterInformation" => self
+                    .device()?
+                    .Activate::<IAudioMeterInformation>(CLSCTX_ALL, None)?
+                    .cast()?,
+                "IAudioSessionManager2" => self
+                    .device()?
+                    .Activate::<IAudioSessionManager2>(CLSCTX_ALL, None)?
+                    .cast()?,
+                "IAudioClient" => self
+                    .device()?
+                    .Activate::<IAudioClient>(CLSCTX_ALL, None)?
+                    .cast()?,
+                _ => bail!("unsupported interface {:?}", interface),
+            })
+        }
+    }
+
+    pub fn get_property(&self, key: &PROPERTYKEY) -> anyhow::Result<PropertyValue> {
+        read_property(&self.device()?, key)
+    }
+
+    /// The id of the physical device this endpoint belongs to, shared with its other endpoints
+    pub fn container_id(&self) -> anyhow::Result<Option<GUID>> {
+        match self.get_property(&PKEY_Device_ContainerId)? {
+            PropertyValue::Guid(guid) => Ok(Some(guid)),
+            _ => Ok(None),
+        }
+    }
+
+    /// The endpoint's own GUID, as used for its registry key. Windows stores it as a string.
+    pub fn endpoint_guid(&self) -> anyhow::Result<Option<GUID>> {
+        match self.get_property(&PKEY_AudioEndpoint_GUID)? {
+            PropertyValue::String(text) => Ok(Some(parse_guid(&text)?)),
+            PropertyValue::Guid(guid) => Ok(Some(guid)),
+            _ => Ok(None),
+        }
+    }
+
+    /// The bus the device is attached through, e.g. `USB`, `BTHENUM` or `HDAUDIO`
+    pub fn enumerator_name(&self) -> anyhow::Result<Option<String>> {
+        match self.get_property(&PKEY_Device_EnumeratorName)? {
+            PropertyValue::String(name) => Ok(Some(name)),
+            _ => Ok(None),
+        }
+    }
+
+    /// The `SPEAKER_*` channel mask of the physical speakers, or 0 if the endpoint doesn't say
+    pub fn physical_speakers(&self) -> anyhow::Result<u32> {
+        match self.get_property(&PKEY_AudioEndpoint_PhysicalSpeakers)? {
+            PropertyValue::UInt(mask) => Ok(mask),
+            _ => Ok(0),
+        }
+    }
+
+    /// Read an on/off property, which endpoints store either as a bool or a 0/1 integer
+    fn property_flag(&self, key: &PROPERTYKEY) -> anyhow::Result<Option<bool>> {
+        match self.get_property(key)? {
+            PropertyValue::Bool(flag) => Ok(Some(flag)),
+            PropertyValue::UInt(value) => Ok(Some(value != 0)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Whether applications may take exclusive control of the device, or None if not known
+    pub fn exclusive_mode_allowed(&self) -> anyhow::Result<Option<bool>> {
+        self.property_flag(&PKEY_EXCLUSIVE_MODE_ALLOWED)
+    }
+
+    /// Whether the driver supports event-driven (rather than polled) streams, or None if not known
+    pub fn supports_event_driven_mode(&self) -> anyhow::Result<Option<bool>> {
+        self.property_flag(&PKEY_AudioEndpoint_Supports_EventDriven_Mode)
+    }
+
+    /// Whether the endpoint's audio enhancements (its "system effects") are turned on
+    ///
+    /// Endpoints that don't have the setting haven't had enhancements turned off.
+    pub fn enhancements_enabled(&self) -> anyhow::Result<bool> {
+        match self.get_property(&PKEY_AudioEndpoint_Disable_SysFx)? {
+            PropertyValue::UInt(value) => Ok(value != ENDPOINT_SYSFX_DISABLED),
+            _ => Ok(true),
+        }
+    }
+
+    /// Turn the endpoint's audio enhancements on or off, which needs administrator rights
+    pub fn set_enhancements_enabled(&self, enabled: bool) -> Result<()> {
+        let value = match enabled {
+            true => ENDPOINT_SYSFX_ENABLED,
+            false => ENDPOINT_SYSFX_DISABLED,
+        };
+        write_uint_property(&self.device()?, &PKEY_AudioEndpoint_Disable_SysFx, value)
+    }
+
+    /// Send volume changes of this device to `channel`, replacing any previous registration
+    ///
+    /// With `emit_initial` the current state is sent first, ahead of any real notification.
+    /// Unless `with_channels` is set, events leave `channel_volumes` empty, and unless `with_db` is
+    /// set they leave `volume_db` as None. Notifications that arrive less than `min_interval` after
+    /// the last one sent are coalesced into the latest.
+    pub fn register_volume_change(
+        &mut self,
+        channel: DrainSender<VolumeChangeEvent>,
+        emit_initial: bool,
+        with_channels: bool,
+        with_db: bool,
+        min_interval: Duration,
+    ) -> Result<()> {
+        if emit_initial {
+            let mut initial = self.volume_state()?;
+            if !with_channels {
+                initial.channel_volumes = Box::default();
+            }
+            if !with_db {
+                initial.volume_db = None;
+            }
+            // Nothing else can be sending on the channel yet, so this is always the first item
+            channel.send(initial);
+        }
+
+        let listener = self.listen_volume_change(channel, with_channels, with_db, min_interval)?;
 
         if self.volume_listener.is_some() {
             self.stop_listening()
         }
-
-        self.volume_listener = Some(AgileReference::new(&vcallback)?);
+        self.volume_listener = Some(listener);
 
         Ok(())
     }
 
+    /// Send volume changes of this device to `channel` until the returned listener is stopped
+    ///
+    /// Unlike `register_volume_change` this leaves any other registration in place, so several
+    /// listeners can be active on the same device.
+    pub fn listen_volume_change(
+        &self,
+        channel: DrainSender<VolumeChangeEvent>,
+        with_channels: bool,
+        with_db: bool,
+        min_interval: Duration,
+    ) -> Result<VolumeListener> {
+        let vcallback = VolumeCallbackClient::new(
+            &self.device()?,
+            channel,
+            with_channels,
+            with_db,
+            min_interval,
+        )?;
+
+        let callback = AgileReference::new(&vcallback)?;
+        let listeners = self.enumerator.as_ref().map(|e| e.listeners().clone());
+        if let Some(listeners) = &listeners {
+            listeners.volume.insert(&vcallback, callback.clone());
+        }
+        Ok(VolumeListener {
+            callback,
+            listeners,
+        })
+    }
+
+    /// The current volume listener, which can be stopped without access to this device
+    pub fn volume_listener(&self) -> Option<VolumeListener> {
+        self.volume_listener.clone()
+    }
+
     pub fn stop_listening(&mut self) {
-        if let Some(agile_ref) = self.volume_listener.as_ref() {
-            unsafe {
-                if let Ok(interface) = agile_ref.resolve() {
-                    let cb = interface.as_impl();
-                    debug!("Stop listening to changes from {:?}", self.friendly_name);
-                    let _ = cb.endpoint.UnregisterControlChangeNotify(&interface);
-                }
-            }
-            self.volume_listener = None;
+        if let Some(listener) = self.volume_listener.take() {
+            debug!("Stop listening to changes from {:?}", self.friendly_name);
+            listener.stop();
         }
     }
 
     pub fn set_default(&self, role: ERole) -> Result<()> {
-        let mut text = self.id.encode_utf16().collect::<Vec<_>>();
-        text.push(0);
-        let wstr = PCWSTR::from_raw(text.as_ptr());
-        unsafe {
-            let policy_config: IPolicyConfig = CoCreateInstance(&PolicyConfig, None, CLSCTX_ALL)?;
+        set_default_endpoint(&self.id, role)
+    }
+}
 
-            policy_config.SetDefaultEndpoint(wstr, role).ok()?;
+/// A volume listener registered by `AudioDevice::register_volume_change`
+#[derive(Clone)]
+pub struct VolumeListener {
+    callback: AgileReference<IAudioEndpointVolumeCallback>,
+    listeners: Option<Arc<Listeners>>,
+}
+
+impl VolumeListener {
+    /// Stop the listener receiving volume changes. Stopping it again does nothing.
+    pub fn stop(&self) {
+        if let Some(listeners) = &self.listeners {
+            listeners.volume.remove_reference(&self.callback);
+        }
+        if let Ok(interface) = self.callback.resolve() {
+            unsafe {
+                let cb: &VolumeCallbackClient = interface.as_impl();
+                let _ = cb.endpoint.UnregisterControlChangeNotify(&interface);
+            }
         }
+    }
+}
 
-        Ok(())
+/// Stop `callback` receiving volume changes, ending the event stream it was sending to
+pub fn close_volume_callback(callback: &IAudioEndpointVolumeCallback) -> Result<()> {
+    unsafe {
+        let cb: &VolumeCallbackClient = callback.as_impl();
+        cb.channel.close();
+        cb.endpoint.UnregisterControlChangeNotify(callback)
+    }
+}
+
+/// Make the device with the given id the default for `role`
+pub fn set_default_endpoint(device_id: &str, role: ERole) -> Result<()> {
+    let mut text = device_id.encode_utf16().collect::<Vec<_>>();
+    text.push(0);
+    let wstr = PCWSTR::from_raw(text.as_ptr());
+    unsafe {
+        // IPolicyConfig is undocumented, so try the older variant of it before giving up
+        let err = match CoCreateInstance::<_, IPolicyConfig>(&PolicyConfig, None, CLSCTX_ALL) {
+            Ok(policy_config) => return policy_config.SetDefaultEndpoint(wstr, role).ok(),
+            Err(err) => err,
+        };
+        debug!(
+            "IPolicyConfig unavailable ({}), trying IPolicyConfigVista",
+            err
+        );
+
+        match CoCreateInstance::<_, IPolicyConfigVista>(&PolicyConfigVista, None, CLSCTX_ALL) {
+            Ok(policy_config) => policy_config.SetDefaultEndpoint(wstr, role).ok(),
+            Err(_) => Err(Error::new(
+                err.code(),
+                "Changing the default device is not supported by this version of Windows \
+                 (the undocumented PolicyConfig interface is unavailable)"
+                    .into(),
+            )),
+        }
     }
 }
 
@@ -113,30 +619,247 @@ impl Drop for AudioDevice {
     }
 }
 
+/// An activated peak meter, so that polling it doesn't re-activate the interface each time
+pub struct AudioMeter(IAudioMeterInformation);
+
+impl AudioMeter {
+    pub fn peak_value(&self) -> Result<f32> {
+        unsafe { self.0.GetPeakValue() }
+    }
+
+    pub fn channel_peaks(&self) -> Result<Box<[f32]>> {
+        unsafe {
+            let count = self.0.GetMeteringChannelCount()?;
+            let mut peaks = vec![0.0; count as usize];
+            self.0.GetChannelsPeakValues(&mut peaks)?;
+            Ok(peaks.into())
+        }
+    }
+}
+
+/// Soft bounds on a device's master volume
+///
+/// These are only advisory: they are applied by this crate's own volume setters, while other
+/// applications and the volume keys can still go past them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VolumeLimits {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl VolumeLimits {
+    pub fn clamp(&self, volume: f32) -> f32 {
+        volume.clamp(self.min, self.max)
+    }
+}
+
+/// An activated volume control, so that a series of operations only activates it once
+///
+/// Volume changes are kept within the limits of the device it came from, if it has any.
+pub struct EndpointVolume(IAudioEndpointVolume, Option<VolumeLimits>);
+
+impl EndpointVolume {
+    pub fn volume(&self) -> Result<f32> {
+        unsafe { self.0.GetMasterVolumeLevelScalar() }
+    }
+
+    pub fn set_volume(&self, volume: f32) -> Result<()> {
+        let volume = match self.1 {
+            Some(limits) => limits.clamp(volume),
+            None => volume,
+        };
+        unsafe { self.0.SetMasterVolumeLevelScalar(volume, &LIBRARY_CONTEXT) }
+    }
+
+    pub fn mute(&self) -> Result<bool> {
+        Ok(unsafe { self.0.GetMute()? }.as_bool())
+    }
+
+    pub fn set_mute(&self, muted: bool) -> Result<()> {
+        unsafe { self.0.SetMute(muted, &LIBRARY_CONTEXT) }
+    }
+
+    pub fn step(&self, up: bool) -> Result<()> {
+        unsafe {
+            if up {
+                self.0.VolumeStepUp(&LIBRARY_CONTEXT)?
+            } else {
+                self.0.VolumeStepDown(&LIBRARY_CONTEXT)?
+            }
+        }
+        // Steps are the endpoint's own, so may go past the limits; pull the volume back if so
+        if let Some(limits) = self.1 {
+            let volume = self.volume()?;
+            if limits.clamp(volume) != volume {
+                self.set_volume(limits.clamp(volume))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn channel_count(&self) -> Result<u32> {
+        unsafe { self.0.GetChannelCount() }
+    }
+
+    pub fn range(&self) -> Result<VolumeRange> {
+        let mut range = VolumeRange {
+            min_db: 0.0,
+            max_db: 0.0,
+            step_db: 0.0,
+        };
+        unsafe {
+            self.0
+                .GetVolumeRange(&mut range.min_db, &mut range.max_db, &mut range.step_db)?
+        };
+        Ok(range)
+    }
+}
+
+/// A device's volume control and peak meter, activated once so polling them is cheap
+///
+/// Held as agile references so that a poll can be made without the GIL, from whichever thread.
+pub struct LevelPoller {
+    volume: AgileReference<IAudioEndpointVolume>,
+    meter: AgileReference<IAudioMeterInformation>,
+}
+
+impl LevelPoller {
+    /// Read the master volume, mute state and peak level together
+    pub fn poll(&self) -> Result<(f32, bool, f32)> {
+        let volume = self.volume.resolve()?;
+        let meter = self.meter.resolve()?;
+        unsafe {
+            Ok((
+                volume.GetMasterVolumeLevelScalar()?,
+                volume.GetMute()?.as_bool(),
+                meter.GetPeakValue()?,
+            ))
+        }
+    }
+}
+
+/// Peak-hold state for a meter display: the highest recent peak is held for `hold`, then falls
+/// linearly to zero over the same time again, unless a higher peak arrives
+#[derive(Debug, Clone)]
+pub struct PeakHold {
+    hold: Duration,
+    held: f32,
+    since: Instant,
+}
+
+impl PeakHold {
+    pub fn new(hold: Duration) -> Self {
+        PeakHold {
+            hold,
+            held: 0.0,
+            since: Instant::now(),
+        }
+    }
+
+    /// Record a `peak` measured at `now`, returning the held value to show
+    pub fn update(&mut self, peak: f32, now: Instant) -> f32 {
+        let shown = self.shown(now);
+        if peak >= shown {
+            self.held = peak;
+            self.since = now;
+            peak
+        } else {
+            shown
+        }
+    }
+
+    fn shown(&self, now: Instant) -> f32 {
+        let elapsed = now.saturating_duration_since(self.since);
+        if elapsed <= self.hold {
+            return self.held;
+        }
+        let falling = (elapsed - self.hold).as_secs_f32() / self.hold.as_secs_f32();
+        self.held * (1.0 - falling).max(0.0)
+    }
+}
+
 #[derive(Debug)]
 pub struct VolumeChangeEvent {
     pub mute: bool,
     pub volume: f32,
+    /// Master volume in dB, as the endpoint maps `volume` (None unless the listener asked for it,
+    /// or if it couldn't be read)
+    pub volume_db: Option<f32>,
     pub channel_volumes: Box<[f32]>,
+    /// The context passed by whoever made the change, or None if they passed none (GUID_NULL)
+    pub context: Option<GUID>,
+}
+
+impl VolumeChangeEvent {
+    /// The parts of the event that identify a repeated notification
+    pub fn state(&self) -> (bool, f32, Box<[f32]>) {
+        (self.mute, self.volume, self.channel_volumes.clone())
+    }
+
+    /// Read a notification, leaving `channel_volumes` empty unless `with_channels` is set
+    ///
+    /// # Safety
+    ///
+    /// `pnotify` must point to a valid notification, followed by its `nChannels` channel volumes
+    unsafe fn from_notification(
+        pnotify: *const AUDIO_VOLUME_NOTIFICATION_DATA,
+        with_channels: bool,
+    ) -> Self {
+        let notify = *pnotify;
+        let channel_volumes = match notify.nChannels {
+            // Some virtual endpoints have no channels, but still have a master volume and mute
+            0 => Box::default(),
+            _ if !with_channels => Box::default(),
+            // afChannelVolumes is defined as a array of 1, but it's actually an array of nChannels.
+            n => {
+                let start = std::ptr::addr_of!((*pnotify).afChannelVolumes) as *const f32;
+                std::slice::from_raw_parts(start, n as usize).into()
+            }
+        };
+
+        VolumeChangeEvent {
+            mute: notify.bMuted.as_bool(),
+            volume: notify.fMasterVolume,
+            volume_db: None,
+            channel_volumes,
+            context: Some(notify.guidEventContext).filter(|guid| *guid != GUID::zeroed()),
+        }
+    }
 }
 
 #[implement(IAudioEndpointVolumeCallback)]
 pub struct VolumeCallbackClient {
     endpoint: IAudioEndpointVolume,
-    channel: Sender<VolumeChangeEvent>,
+    // Only held when the listener asked for `volume_db`, as an agile reference so that it can be
+    // read from the task that sends an event the throttle held back
+    db_endpoint: Option<AgileReference<IAudioEndpointVolume>>,
+    // Shared with the task that sends an event the throttle held back
+    channel: Arc<DrainSender<VolumeChangeEvent>>,
+    with_channels: bool,
+    throttle: Arc<Throttle<VolumeChangeEvent>>,
 }
 
 impl VolumeCallbackClient {
     #[allow(clippy::new_ret_no_self)]
     fn new(
         device: &IMMDevice,
-        channel: Sender<VolumeChangeEvent>,
+        channel: DrainSender<VolumeChangeEvent>,
+        with_channels: bool,
+        with_db: bool,
+        min_interval: Duration,
     ) -> Result<IAudioEndpointVolumeCallback> {
         let endpoint: IAudioEndpointVolume = unsafe { device.Activate(CLSCTX_ALL, None)? };
+        let db_endpoint = match with_db {
+            true => Some(AgileReference::new(&endpoint)?),
+            false => None,
+        };
 
         let val = VolumeCallbackClient {
             endpoint: endpoint.clone(),
-            channel,
+            db_endpoint,
+            channel: Arc::new(channel),
+            with_channels,
+            throttle: Arc::new(Throttle::new(min_interval)),
         };
 
         unsafe {
@@ -145,33 +868,160 @@ impl VolumeCallbackClient {
             Ok(i_cb)
         }
     }
+
+    /// Send `event` on, first filling in its `volume_db` if the listener asked for it
+    ///
+    /// The notification only carries the scalar volume, and how the endpoint maps that to dB isn't
+    /// exposed, so the level is read from the endpoint as the event goes out. During a burst of
+    /// changes it can be a little newer than `volume`, but the burst's last event catches up.
+    fn emit(
+        db_endpoint: &Option<AgileReference<IAudioEndpointVolume>>,
+        channel: &DrainSender<VolumeChangeEvent>,
+        mut event: VolumeChangeEvent,
+    ) {
+        if let Some(endpoint) = db_endpoint {
+            event.volume_db = endpoint
+                .resolve()
+                .and_then(|endpoint| unsafe { endpoint.GetMasterVolumeLevel() })
+                .ok();
+        }
+        channel.send(event);
+    }
 }
 
 impl IAudioEndpointVolumeCallback_Impl for VolumeCallbackClient {
     #[allow(clippy::not_unsafe_ptr_arg_deref)]
-    fn OnNotify(
-        &self,
-        pnotify: *mut windows::Win32::Media::Audio::AUDIO_VOLUME_NOTIFICATION_DATA,
-    ) -> Result<()> {
-        let notify;
-        let volumes;
-        unsafe {
-            notify = *pnotify;
-            // afChannelVolumes is defined as a array of 1, but it's actually an array of nChannels.
-            let range = (*pnotify).afChannelVolumes.as_ptr_range();
+    fn OnNotify(&self, pnotify: *mut AUDIO_VOLUME_NOTIFICATION_DATA) -> Result<()> {
+        if pnotify.is_null() {
+            return Ok(());
+        }
+        let event = unsafe { VolumeChangeEvent::from_notification(pnotify, self.with_channels) };
 
-            volumes = std::slice::from_raw_parts(range.start, (notify).nChannels as usize).into();
+        // Send the event up, but don't block this thread waiting for the result. During a storm
+        // of notifications, only the latest is sent once the minimum interval is up.
+        match self.throttle.offer(event, Instant::now()) {
+            Throttled::Now(event) => Self::emit(&self.db_endpoint, &self.channel, event),
+            Throttled::Later(delay) => {
+                let (throttle, channel) = (self.throttle.clone(), self.channel.clone());
+                let db_endpoint = self.db_endpoint.clone();
+                task::spawn(async move {
+                    task::sleep(delay).await;
+                    if let Some(event) = throttle.take_pending(Instant::now()) {
+                        if db_endpoint.is_some() {
+                            com::com_initialized();
+                        }
+                        Self::emit(&db_endpoint, &channel, event);
+                    }
+                });
+            }
+            Throttled::Held => {}
         }
-        let event = VolumeChangeEvent {
-            mute: notify.bMuted.as_bool(),
-            volume: notify.fMasterVolume,
-            channel_volumes: volumes,
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_channel_notification() {
+        let notify = AUDIO_VOLUME_NOTIFICATION_DATA {
+            guidEventContext: GUID::zeroed(),
+            bMuted: true.into(),
+            fMasterVolume: 0.5,
+            nChannels: 0,
+            afChannelVolumes: [0.0],
         };
 
-        let channel = self.channel.clone();
-        // Send the event up, but don't block this thread waiting for the result
-        task::spawn(async move { channel.send(event).await });
+        let event = unsafe { VolumeChangeEvent::from_notification(&notify, true) };
 
-        Ok(())
+        assert!(event.mute);
+        assert_eq!(event.volume, 0.5);
+        assert!(event.channel_volumes.is_empty());
+        assert_eq!(event.context, None);
+    }
+
+    #[test]
+    fn volume_limits_clamp() {
+        let limits = VolumeLimits { min: 0.1, max: 0.6 };
+        assert_eq!(limits.clamp(0.0), 0.1);
+        assert_eq!(limits.clamp(0.3), 0.3);
+        assert_eq!(limits.clamp(1.0), 0.6);
+    }
+
+    #[test]
+    fn notification_without_channels() {
+        let context = GUID::from_u128(0x1da5d803_d492_4edd_8c23_e0c0ffee7f0e);
+        let notify = AUDIO_VOLUME_NOTIFICATION_DATA {
+            guidEventContext: context,
+            bMuted: false.into(),
+            fMasterVolume: 0.25,
+            nChannels: 1,
+            afChannelVolumes: [0.25],
+        };
+
+        let event = unsafe { VolumeChangeEvent::from_notification(&notify, false) };
+        assert_eq!(event.volume, 0.25);
+        assert!(event.channel_volumes.is_empty());
+        assert_eq!(event.context, Some(context));
+
+        let event = unsafe { VolumeChangeEvent::from_notification(&notify, true) };
+        assert_eq!(&*event.channel_volumes, &[0.25]);
+    }
+
+    #[test]
+    fn balance_of_stereo_channels() {
+        assert_eq!(stereo_balance(&[0.5, 0.5]), Some(0.0));
+        assert_eq!(stereo_balance(&[0.0, 0.0]), Some(0.0));
+        assert_eq!(stereo_balance(&[1.0, 0.0]), Some(-1.0));
+        assert_eq!(stereo_balance(&[0.0, 0.8]), Some(1.0));
+        assert_eq!(stereo_balance(&[1.0, 0.5]), Some(-0.5));
+        assert_eq!(stereo_balance(&[1.0]), None);
+        assert_eq!(stereo_balance(&[1.0; 6]), None);
+    }
+
+    #[test]
+    fn normalizes_pasted_ids() {
+        assert_eq!(
+            normalize_device_id("  {0.0.1.00000000}.{B3F8FA53-0004-438E-9003-51A46E139BFC}\n"),
+            "{0.0.1.00000000}.{b3f8fa53-0004-438e-9003-51a46e139bfc}"
+        );
+        assert_eq!(
+            normalize_device_id("{0.0.0.00000000}.{abc}"),
+            "{0.0.0.00000000}.{abc}"
+        );
+    }
+
+    #[test]
+    fn device_id_components() {
+        assert_eq!(
+            split_device_id("{0.0.1.00000000}.{b3f8fa53-0004-438e-9003-51a46e139bfc}"),
+            Some(("{0.0.1.00000000}", "{b3f8fa53-0004-438e-9003-51a46e139bfc}"))
+        );
+        assert_eq!(split_device_id("{0.0.1.00000000}"), None);
+        assert_eq!(split_device_id("{0.0.1.00000000}.{not-a-guid}"), None);
+        assert_eq!(
+            split_device_id("0.0.1}.{b3f8fa53-0004-438e-9003-51a46e139bfc}"),
+            None
+        );
+    }
+
+    #[test]
+    fn peak_hold_holds_then_falls() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut hold = PeakHold::new(Duration::from_millis(100));
+
+        assert_eq!(hold.update(0.8, at(0)), 0.8);
+        assert_eq!(hold.update(0.2, at(50)), 0.8);
+        assert_eq!(hold.update(0.2, at(100)), 0.8);
+        assert!((hold.update(0.2, at(150)) - 0.4).abs() < 1e-6);
+        // Once it has fallen below the current peak, that becomes the held value
+        assert_eq!(hold.update(0.3, at(175)), 0.3);
+        assert_eq!(hold.update(0.0, at(250)), 0.3);
+        assert_eq!(hold.update(0.0, at(400)), 0.0);
+        assert_eq!(hold.update(0.9, at(400)), 0.9);
     }
 }
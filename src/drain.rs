@@ -0,0 +1,353 @@
+//! Forwards notifications from COM callbacks to an async channel, preserving order.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_std::channel::{unbounded, Receiver, Sender, TrySendError};
+use async_std::{future, task};
+
+use crate::enums::SendPolicy;
+
+/// Queue feeding a single long-lived task that sends items on to `channel`.
+///
+/// COM callbacks must not block, so they push onto an unbounded (lock-free) queue and a single
+/// drain task does the (potentially waiting) send. Because there is exactly one task doing the
+/// sending, items arrive in the order they were pushed.
+///
+/// The drain task exits once this sender is dropped, or once the receiving end of `channel` is
+/// closed.
+///
+/// Consumers that only care about the latest state can instead have items dropped when `channel`
+/// is full, with `with_policy`, in which case no queue or task is used.
+pub struct DrainSender<T> {
+    inner: Inner<T>,
+}
+
+enum Inner<T> {
+    Queue(Sender<T>),
+    DropNewest(Sender<T>),
+    // The receiver is used to discard the oldest item to make room
+    DropOldest(Sender<T>, Receiver<T>),
+}
+
+impl<T: Send + 'static> DrainSender<T> {
+    pub fn new(channel: Sender<T>) -> Self {
+        let (queue, pending) = unbounded::<T>();
+
+        task::spawn(async move {
+            while let Ok(item) = pending.recv().await {
+                if channel.send(item).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        DrainSender {
+            inner: Inner::Queue(queue),
+        }
+    }
+
+    /// Like `new`, but with the sending done by a dedicated OS thread instead of an async-std task
+    ///
+    /// The async-std executor runs its tasks on a small shared pool of threads, so a task's sends
+    /// can be held up while those threads are busy with other tasks (e.g. ones waiting for the
+    /// GIL). A thread of its own keeps delivery independent of that, at the cost of a thread per
+    /// sender. The thread exits under the same conditions as the task would.
+    pub fn on_thread(channel: Sender<T>) -> std::io::Result<Self> {
+        let (queue, pending) = unbounded::<T>();
+
+        std::thread::Builder::new()
+            .name("windows-audio-control events".into())
+            .spawn(move || {
+                while let Ok(item) = pending.recv_blocking() {
+                    if channel.send_blocking(item).is_err() {
+                        break;
+                    }
+                }
+            })?;
+
+        Ok(DrainSender {
+            inner: Inner::Queue(queue),
+        })
+    }
+
+    /// Sender for the `channel`/`receiver` pair that behaves according to `policy` when full
+    pub fn with_policy(channel: Sender<T>, receiver: Receiver<T>, policy: SendPolicy) -> Self {
+        let inner = match policy {
+            SendPolicy::Block => return Self::new(channel),
+            SendPolicy::DropNewest => Inner::DropNewest(channel),
+            SendPolicy::DropOldest => Inner::DropOldest(channel, receiver),
+        };
+        DrainSender { inner }
+    }
+
+    /// Close the channel, so the receiver sees the end once it has had the items already sent
+    pub fn close(&self) {
+        match &self.inner {
+            Inner::Queue(queue) => _ = queue.close(),
+            Inner::DropNewest(channel) | Inner::DropOldest(channel, _) => _ = channel.close(),
+        }
+    }
+
+    /// Enqueue `item` without blocking the calling thread.
+    pub fn send(&self, item: T) {
+        match &self.inner {
+            Inner::Queue(queue) => _ = queue.try_send(item),
+            Inner::DropNewest(channel) => _ = channel.try_send(item),
+            Inner::DropOldest(channel, receiver) => {
+                let mut item = item;
+                while let Err(TrySendError::Full(rejected)) = channel.try_send(item) {
+                    _ = receiver.try_recv();
+                    item = rejected;
+                }
+            }
+        }
+    }
+}
+
+/// Forward items from `rx` to `tx`, but only once no newer item has arrived for `quiet`
+///
+/// Bursts of items are coalesced into the last one of the burst.
+pub async fn debounce<T>(rx: Receiver<T>, tx: Sender<T>, quiet: Duration) {
+    while let Ok(mut latest) = rx.recv().await {
+        loop {
+            match future::timeout(quiet, rx.recv()).await {
+                Ok(Ok(newer)) => latest = newer,
+                // The source has closed, so the final value won't be superseded
+                Ok(Err(_)) => {
+                    _ = tx.send(latest).await;
+                    return;
+                }
+                Err(_) => break,
+            }
+        }
+        if tx.send(latest).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Forward items from `rx` to `tx`, dropping any item whose `key` equals that of the item before it
+///
+/// Items with a `key` of None (such as errors) are always forwarded.
+pub async fn dedupe<T, K: PartialEq>(
+    rx: Receiver<T>,
+    tx: Sender<T>,
+    key: impl Fn(&T) -> Option<K>,
+) {
+    let mut last = None;
+    while let Ok(item) = rx.recv().await {
+        let item_key = key(&item);
+        if item_key.is_some() && item_key == last {
+            continue;
+        }
+        last = item_key;
+        if tx.send(item).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Rate limit for items pushed by a callback: at most one item is let through per `interval`
+///
+/// Items that arrive sooner are held back, each replacing the one before, so that only the latest
+/// of a burst is forwarded once the interval is up. An interval of zero lets everything through.
+pub struct Throttle<T> {
+    interval: Duration,
+    state: Mutex<ThrottleState<T>>,
+}
+
+struct ThrottleState<T> {
+    last_sent: Option<Instant>,
+    pending: Option<T>,
+}
+
+/// What `Throttle::offer` decided to do with an item
+#[derive(Debug, PartialEq)]
+pub enum Throttled<T> {
+    /// Send the item now
+    Now(T),
+    /// The item is held back: call `take_pending` after this long to get it (or a newer one)
+    Later(Duration),
+    /// The item replaced one that was already held back, for which `Later` was returned before
+    Held,
+}
+
+impl<T> Throttle<T> {
+    pub fn new(interval: Duration) -> Self {
+        Throttle {
+            interval,
+            state: Mutex::new(ThrottleState {
+                last_sent: None,
+                pending: None,
+            }),
+        }
+    }
+
+    /// Decide what to do with `item`, arriving at `now`
+    pub fn offer(&self, item: T, now: Instant) -> Throttled<T> {
+        let mut state = self.state.lock().unwrap();
+        match state.last_sent.map(|sent| sent + self.interval) {
+            Some(due) if due > now => {
+                let scheduled = state.pending.replace(item).is_some();
+                match scheduled {
+                    true => Throttled::Held,
+                    false => Throttled::Later(due - now),
+                }
+            }
+            _ => {
+                state.last_sent = Some(now);
+                Throttled::Now(item)
+            }
+        }
+    }
+
+    /// Take the latest item held back by `offer`, if any, counting it as sent at `now`
+    pub fn take_pending(&self, now: Instant) -> Option<T> {
+        let mut state = self.state.lock().unwrap();
+        let item = state.pending.take();
+        if item.is_some() {
+            state.last_sent = Some(now);
+        }
+        item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::channel::bounded;
+
+    #[test]
+    fn preserves_order_under_load() {
+        let (tx, rx) = bounded(1);
+        let sender = DrainSender::new(tx);
+
+        for i in 0..10_000u32 {
+            sender.send(i);
+        }
+        drop(sender);
+
+        let received = task::block_on(async {
+            let mut received = Vec::new();
+            while let Ok(i) = rx.recv().await {
+                received.push(i);
+            }
+            received
+        });
+
+        assert_eq!(received, (0..10_000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn thread_preserves_order() {
+        let (tx, rx) = bounded(1);
+        let sender = DrainSender::on_thread(tx).unwrap();
+
+        for i in 0..1_000u32 {
+            sender.send(i);
+        }
+        drop(sender);
+
+        let received = std::iter::from_fn(|| rx.recv_blocking().ok()).collect::<Vec<_>>();
+        assert_eq!(received, (0..1_000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn debounce_coalesces_bursts() {
+        let (burst_tx, burst_rx) = bounded(1);
+        let (tx, rx) = bounded(1);
+        task::spawn(debounce(burst_rx, tx, Duration::from_millis(50)));
+
+        let received = task::block_on(async move {
+            for i in 0..10 {
+                burst_tx.send(i).await.unwrap();
+            }
+            let first = rx.recv().await.unwrap();
+
+            burst_tx.send(10).await.unwrap();
+            drop(burst_tx);
+
+            let mut received = vec![first];
+            while let Ok(i) = rx.recv().await {
+                received.push(i);
+            }
+            received
+        });
+
+        assert_eq!(received, [9, 10]);
+    }
+
+    #[test]
+    fn dedupe_drops_consecutive_repeats() {
+        let (in_tx, in_rx) = bounded(1);
+        let (tx, rx) = bounded(1);
+        task::spawn(dedupe(in_rx, tx, |i: &u32| Some(*i)));
+
+        let received = task::block_on(async move {
+            task::spawn(async move {
+                for i in [1, 1, 2, 2, 2, 1, 3, 3] {
+                    in_tx.send(i).await.unwrap();
+                }
+            });
+            let mut received = Vec::new();
+            while let Ok(i) = rx.recv().await {
+                received.push(i);
+            }
+            received
+        });
+
+        assert_eq!(received, [1, 2, 1, 3]);
+    }
+
+    #[test]
+    fn throttle_holds_back_all_but_latest() {
+        let throttle = Throttle::new(Duration::from_millis(100));
+        let start = Instant::now();
+
+        assert_eq!(throttle.offer(1, start), Throttled::Now(1));
+        assert_eq!(
+            throttle.offer(2, start + Duration::from_millis(30)),
+            Throttled::Later(Duration::from_millis(70))
+        );
+        assert_eq!(
+            throttle.offer(3, start + Duration::from_millis(60)),
+            Throttled::Held
+        );
+
+        let flushed = start + Duration::from_millis(100);
+        assert_eq!(throttle.take_pending(flushed), Some(3));
+        assert_eq!(throttle.take_pending(flushed), None);
+        assert_eq!(
+            throttle.offer(4, flushed + Duration::from_millis(100)),
+            Throttled::Now(4)
+        );
+
+        let unthrottled = Throttle::new(Duration::ZERO);
+        assert_eq!(unthrottled.offer(1, start), Throttled::Now(1));
+        assert_eq!(unthrottled.offer(2, start), Throttled::Now(2));
+    }
+
+    #[test]
+    fn drop_policies_keep_one_end() {
+        let (tx, rx) = bounded(2);
+        let sender = DrainSender::with_policy(tx, rx.clone(), SendPolicy::DropNewest);
+        for i in 0..5 {
+            sender.send(i);
+        }
+        assert_eq!(
+            std::iter::from_fn(|| rx.try_recv().ok()).collect::<Vec<_>>(),
+            [0, 1]
+        );
+
+        let (tx, rx) = bounded(2);
+        let sender = DrainSender::with_policy(tx, rx.clone(), SendPolicy::DropOldest);
+        for i in 0..5 {
+            sender.send(i);
+        }
+        assert_eq!(
+            std::iter::from_fn(|| rx.try_recv().ok()).collect::<Vec<_>>(),
+            [3, 4]
+        );
+    }
+}
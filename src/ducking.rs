@@ -0,0 +1,39 @@
+//! The system-wide "communications activity" ducking setting from the Sound control panel.
+//!
+//! There is no public API for this setting. It lives in the current user's registry, where it can
+//! be read; changes written there are not picked up by the audio service until it restarts, so
+//! only reading is supported.
+
+use windows::{
+    w,
+    Win32::{
+        Foundation::{ERROR_FILE_NOT_FOUND, ERROR_SUCCESS},
+        System::Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD},
+    },
+};
+
+use super::enums::DuckingPreference;
+
+pub fn ducking_preference() -> anyhow::Result<DuckingPreference> {
+    let mut value = 0u32;
+    let mut size = std::mem::size_of::<u32>() as u32;
+
+    let err = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            w!("Software\\Microsoft\\Multimedia\\Audio"),
+            w!("UserDuckingPreference"),
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut value as *mut u32 as *mut _),
+            Some(&mut size),
+        )
+    };
+
+    match err {
+        ERROR_SUCCESS => Ok(DuckingPreference::try_from(value)?),
+        // Never changed from the default
+        ERROR_FILE_NOT_FOUND => Ok(DuckingPreference::Reduce80),
+        err => Err(windows::core::Error::from(err.to_hresult()).into()),
+    }
+}
@@ -22,6 +22,25 @@ pub enum DataFlow {
     All = eAll.0,
 }
 
+impl DataFlow {
+    pub fn py_name(self) -> &'static str {
+        match self {
+            DataFlow::Render => "RENDER",
+            DataFlow::Capture => "CAPTURE",
+            DataFlow::All => "ALL",
+        }
+    }
+
+    pub fn from_py_name(name: &str) -> Option<Self> {
+        match name {
+            "RENDER" => Some(DataFlow::Render),
+            "CAPTURE" => Some(DataFlow::Capture),
+            "ALL" => Some(DataFlow::All),
+            _ => None,
+        }
+    }
+}
+
 impl From<DataFlow> for EDataFlow {
     fn from(e: DataFlow) -> Self {
         Self(e as i32)
@@ -40,12 +59,54 @@ pub enum Role {
     Multimedia = eMultimedia.0,
 }
 
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Console, Role::Multimedia, Role::Communications];
+
+    pub fn py_name(self) -> &'static str {
+        match self {
+            Role::Console => "CONSOLE",
+            Role::Communications => "COMMS",
+            Role::Multimedia => "MULTIMEDIA",
+        }
+    }
+}
+
 impl From<Role> for ERole {
     fn from(e: Role) -> Self {
         Self(e as i32)
     }
 }
 
+/// What Windows does to other sounds when it detects communications activity
+#[derive(Debug, Eq, PartialEq, TryFromPrimitive, Clone, Copy)]
+#[pyclass(name = "DuckingPreference")]
+#[repr(u32)]
+pub enum DuckingPreference {
+    #[pyo3(name = "MUTE_OTHERS")]
+    MuteOthers = 0,
+    #[pyo3(name = "REDUCE_80")]
+    Reduce80 = 1,
+    #[pyo3(name = "REDUCE_50")]
+    Reduce50 = 2,
+    #[pyo3(name = "DO_NOTHING")]
+    DoNothing = 3,
+}
+
+/// What to do with a notification when the consumer hasn't caught up with the previous ones
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[pyclass(name = "SendPolicy")]
+pub enum SendPolicy {
+    /// Queue it, so that every notification is delivered in order
+    #[pyo3(name = "BLOCK")]
+    Block,
+    /// Drop the oldest undelivered notification to make room for it
+    #[pyo3(name = "DROP_OLDEST")]
+    DropOldest,
+    /// Drop it
+    #[pyo3(name = "DROP_NEWEST")]
+    DropNewest,
+}
+
 bitflags! {
     #[derive(Debug, Eq, PartialEq, Clone, Copy)]
     pub struct DeviceState: u32 {
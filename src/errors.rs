@@ -1,8 +1,14 @@
 use pyo3::{
-    exceptions::{PyOSError, PyRuntimeError},
+    exceptions::{
+        PyMemoryError, PyNotImplementedError, PyOSError, PyPermissionError, PyRuntimeError,
+    },
     PyErr,
 };
 use thiserror::Error;
+use windows::Win32::{
+    Foundation::{E_ACCESSDENIED, E_NOTIMPL, E_OUTOFMEMORY},
+    Media::Audio::AUDCLNT_E_DEVICE_INVALIDATED,
+};
 
 #[derive(Error, Debug)]
 pub enum WindowsAudioError {
@@ -15,8 +21,58 @@ pub enum WindowsAudioError {
 impl From<WindowsAudioError> for PyErr {
     fn from(err: WindowsAudioError) -> Self {
         match err {
+            // Unplugging hot-pluggable devices mid-operation is common enough to deserve a clear message
+            WindowsAudioError::WindowsErr(e) if e.code() == AUDCLNT_E_DEVICE_INVALIDATED => {
+                PyRuntimeError::new_err("The audio device was disconnected (unplugged or disabled)")
+            }
+            WindowsAudioError::WindowsErr(e) if e.code() == E_ACCESSDENIED => {
+                PyPermissionError::new_err(e.to_string())
+            }
+            WindowsAudioError::WindowsErr(e) if e.code() == E_NOTIMPL => {
+                PyNotImplementedError::new_err(e.to_string())
+            }
+            WindowsAudioError::WindowsErr(e) if e.code() == E_OUTOFMEMORY => {
+                PyMemoryError::new_err(e.to_string())
+            }
             WindowsAudioError::WindowsErr(e) => PyOSError::new_err(e.to_string()),
             _ => PyRuntimeError::new_err(err.to_string()),
         }
     }
 }
+
+/// Convert any error to the most specific Python exception for it
+///
+/// Windows errors get the same exception types whether or not they have been wrapped in an
+/// `anyhow::Error` (or had context added) along the way.
+pub fn into_pyerr(err: anyhow::Error) -> PyErr {
+    let err = match err.downcast::<PyErr>() {
+        Ok(err) => return err,
+        Err(err) => err,
+    };
+    let err = match err.downcast::<WindowsAudioError>() {
+        Ok(err) => return err.into(),
+        Err(err) => err,
+    };
+    match err.downcast::<windows::core::Error>() {
+        Ok(err) => WindowsAudioError::from(err).into(),
+        Err(err) => PyRuntimeError::new_err(format!("{:#}", err)),
+    }
+}
+
+/// Error returned from the Python-facing methods, converted to Python with `into_pyerr`
+#[derive(Debug)]
+pub struct PyAudioError(anyhow::Error);
+
+impl<E: Into<anyhow::Error>> From<E> for PyAudioError {
+    fn from(err: E) -> Self {
+        PyAudioError(err.into())
+    }
+}
+
+impl From<PyAudioError> for PyErr {
+    fn from(err: PyAudioError) -> Self {
+        into_pyerr(err.0)
+    }
+}
+
+pub type Result<T, E = PyAudioError> = std::result::Result<T, E>;
@@ -0,0 +1,387 @@
+//! An in-memory `DeviceSource` for tests, with scripted devices and events.
+//!
+//! Its devices and events go through COM just as the system's do, and COM (like Python) is only
+//! linked on Windows, so this and the tests using it only build there.
+
+use std::{ffi::c_void, sync::Mutex};
+
+use windows::{
+    core::{implement, GUID, PCWSTR, PWSTR},
+    Win32::{
+        Devices::FunctionDiscovery::PKEY_Device_FriendlyName,
+        Foundation::{E_ACCESSDENIED, E_INVALIDARG, E_NOINTERFACE},
+        Media::Audio::{
+            EDataFlow, ERole, IMMDevice, IMMDevice_Impl, IMMEndpoint, IMMEndpoint_Impl,
+            IMMNotificationClient,
+        },
+        System::Com::{
+            CoTaskMemAlloc, IAgileObject, IAgileObject_Impl, StructuredStorage::PROPVARIANT,
+            CLSCTX, STGM, VT_LPWSTR,
+        },
+        UI::Shell::PropertiesSystem::{IPropertyStore, IPropertyStore_Impl, PROPERTYKEY},
+    },
+};
+
+use crate::collection::{DeviceCollection, DeviceNotificationEvent, NotificationClient};
+use crate::device::{normalize_device_id, AudioDevice};
+use crate::enums;
+use crate::errors::WindowsAudioError;
+use crate::source::DeviceSource;
+use crate::ELEMENT_NOT_FOUND;
+
+/// In-memory device source with a scripted set of devices, defaults and events
+///
+/// The devices it returns are backed by `FakeEndpoint`s. Events are scripted with `notify`, which
+/// delivers them to every registered notification client as Windows would.
+#[derive(Default)]
+pub struct FakeDeviceSource {
+    /// Id, friendly name, data flow and state of each device
+    pub devices: Vec<(String, String, enums::DataFlow, enums::DeviceState)>,
+    pub defaults: Vec<(EDataFlow, ERole, String)>,
+    clients: Mutex<Vec<IMMNotificationClient>>,
+}
+
+// The registered clients are all `NotificationClient`s, which can be called from any thread
+unsafe impl Send for FakeDeviceSource {}
+unsafe impl Sync for FakeDeviceSource {}
+
+impl FakeDeviceSource {
+    /// Active speakers (the default output), an unplugged headset and an active microphone
+    pub fn sample() -> Self {
+        use enums::{DataFlow, DeviceState, Role};
+
+        FakeDeviceSource {
+            devices: vec![
+                (
+                    "speakers".into(),
+                    "Speakers".into(),
+                    DataFlow::Render,
+                    DeviceState::Active,
+                ),
+                (
+                    "headset".into(),
+                    "Headset".into(),
+                    DataFlow::Render,
+                    DeviceState::Unplugged,
+                ),
+                (
+                    "mic".into(),
+                    "Microphone".into(),
+                    DataFlow::Capture,
+                    DeviceState::Active,
+                ),
+            ],
+            defaults: vec![(
+                DataFlow::Render.into(),
+                Role::Console.into(),
+                "speakers".into(),
+            )],
+            clients: Mutex::default(),
+        }
+    }
+
+    /// Deliver `event` to every registered notification client
+    pub fn notify(&self, event: &DeviceNotificationEvent) -> windows::core::Result<()> {
+        let device_id = event
+            .device_id()
+            .encode_utf16()
+            .chain([0])
+            .collect::<Vec<_>>();
+        let device_id = PCWSTR::from_raw(device_id.as_ptr());
+
+        for client in self.clients.lock().unwrap().iter() {
+            unsafe {
+                match event {
+                    DeviceNotificationEvent::StateChanged(_, state) => {
+                        client.OnDeviceStateChanged(device_id, (*state).into())?
+                    }
+                    DeviceNotificationEvent::Added(_) => client.OnDeviceAdded(device_id)?,
+                    DeviceNotificationEvent::Removed(_) => client.OnDeviceRemoved(device_id)?,
+                    DeviceNotificationEvent::DefaultChanged(_, flow, role) => {
+                        client.OnDefaultDeviceChanged((*flow).into(), (*role).into(), device_id)?
+                    }
+                    DeviceNotificationEvent::PropertyValueChanged(_, key) => {
+                        client.OnPropertyValueChanged(device_id, *key)?
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of notification clients currently registered
+    pub fn client_count(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+}
+
+impl DeviceSource for FakeDeviceSource {
+    fn get_device(&self, device_id: &str) -> anyhow::Result<AudioDevice> {
+        let device_id = normalize_device_id(device_id);
+        let (id, name, data_flow, state) = self
+            .devices
+            .iter()
+            .find(|(id, ..)| normalize_device_id(id) == device_id)
+            // What the system's enumerator says for an id it doesn't know
+            .ok_or_else(|| {
+                WindowsAudioError::from(windows::core::Error::from(ELEMENT_NOT_FOUND))
+            })?;
+
+        let endpoint = FakeEndpoint {
+            id: id.clone(),
+            name: name.clone(),
+            data_flow: *data_flow,
+            state: *state,
+        };
+        AudioDevice::new(endpoint.into())
+    }
+
+    fn get_collection(
+        &self,
+        _dataflow: enums::DataFlow,
+        _state_mask: enums::DeviceState,
+    ) -> anyhow::Result<DeviceCollection> {
+        anyhow::bail!("fake device source has no device collections")
+    }
+
+    fn device_ids(
+        &self,
+        dataflow: enums::DataFlow,
+        state_mask: enums::DeviceState,
+    ) -> anyhow::Result<Vec<String>> {
+        Ok(self
+            .devices
+            .iter()
+            .filter(|(_, _, flow, state)| {
+                (dataflow == enums::DataFlow::All || *flow == dataflow)
+                    && state_mask.intersects(*state)
+            })
+            .map(|(id, ..)| id.clone())
+            .collect())
+    }
+
+    fn get_default_device(&self, dataflow: EDataFlow, role: ERole) -> anyhow::Result<AudioDevice> {
+        match self.get_default_device_id(dataflow, role)? {
+            Some(device_id) => self.get_device(&device_id),
+            None => {
+                Err(WindowsAudioError::from(windows::core::Error::from(ELEMENT_NOT_FOUND)).into())
+            }
+        }
+    }
+
+    fn get_default_device_id(
+        &self,
+        dataflow: EDataFlow,
+        role: ERole,
+    ) -> anyhow::Result<Option<String>> {
+        Ok(self
+            .defaults
+            .iter()
+            .find(|(flow, r, _)| *flow == dataflow && *r == role)
+            .map(|(_, _, id)| id.clone()))
+    }
+
+    fn register_notification(&self, client: &IMMNotificationClient) -> anyhow::Result<()> {
+        self.clients.lock().unwrap().push(client.clone());
+        Ok(())
+    }
+
+    fn unregister_notification(&self, client: &IMMNotificationClient) -> anyhow::Result<()> {
+        self.clients.lock().unwrap().retain(|c| c != client);
+        Ok(())
+    }
+
+    fn close_all(&self) -> anyhow::Result<()> {
+        for client in std::mem::take(&mut *self.clients.lock().unwrap()) {
+            NotificationClient::close(&client);
+        }
+        Ok(())
+    }
+}
+
+/// An in-memory endpoint, for `FakeDeviceSource` to make devices of
+///
+/// It has an id, state, data flow and friendly name (its other properties are empty), but no
+/// interfaces to activate. Like the system's endpoints it is agile, so it can be held in an
+/// `AgileReference`.
+#[implement(IMMDevice, IMMEndpoint, IAgileObject)]
+struct FakeEndpoint {
+    id: String,
+    name: String,
+    data_flow: enums::DataFlow,
+    state: enums::DeviceState,
+}
+
+impl IMMDevice_Impl for FakeEndpoint {
+    fn Activate(
+        &self,
+        _iid: *const GUID,
+        _dwclsctx: CLSCTX,
+        _pactivationparams: *const PROPVARIANT,
+        _ppinterface: *mut *mut c_void,
+    ) -> windows::core::Result<()> {
+        Err(E_NOINTERFACE.into())
+    }
+
+    fn OpenPropertyStore(&self, _stgmaccess: STGM) -> windows::core::Result<IPropertyStore> {
+        Ok(FakeProperties {
+            name: self.name.clone(),
+        }
+        .into())
+    }
+
+    fn GetId(&self) -> windows::core::Result<PWSTR> {
+        Ok(co_task_string(&self.id))
+    }
+
+    fn GetState(&self) -> windows::core::Result<u32> {
+        Ok(self.state.into())
+    }
+}
+
+impl IMMEndpoint_Impl for FakeEndpoint {
+    fn GetDataFlow(&self) -> windows::core::Result<EDataFlow> {
+        Ok(self.data_flow.into())
+    }
+}
+
+impl IAgileObject_Impl for FakeEndpoint {}
+
+/// The read-only property store of a `FakeEndpoint`
+#[implement(IPropertyStore)]
+struct FakeProperties {
+    name: String,
+}
+
+impl IPropertyStore_Impl for FakeProperties {
+    fn GetCount(&self) -> windows::core::Result<u32> {
+        Ok(1)
+    }
+
+    fn GetAt(&self, iprop: u32) -> windows::core::Result<PROPERTYKEY> {
+        match iprop {
+            0 => Ok(PKEY_Device_FriendlyName),
+            _ => Err(E_INVALIDARG.into()),
+        }
+    }
+
+    fn GetValue(&self, key: *const PROPERTYKEY) -> windows::core::Result<PROPVARIANT> {
+        let mut prop = PROPVARIANT::default();
+        unsafe {
+            if *key == PKEY_Device_FriendlyName {
+                let inner = &mut prop.Anonymous.Anonymous;
+                inner.vt = VT_LPWSTR;
+                inner.Anonymous.pwszVal = co_task_string(&self.name);
+            }
+        }
+        Ok(prop)
+    }
+
+    fn SetValue(
+        &self,
+        _key: *const PROPERTYKEY,
+        _propvar: *const PROPVARIANT,
+    ) -> windows::core::Result<()> {
+        Err(E_ACCESSDENIED.into())
+    }
+
+    fn Commit(&self) -> windows::core::Result<()> {
+        Err(E_ACCESSDENIED.into())
+    }
+}
+
+/// `text` as a wide string allocated with `CoTaskMemAlloc`, as callers free it with
+/// `CoTaskMemFree`
+fn co_task_string(text: &str) -> PWSTR {
+    let wide = text.encode_utf16().chain([0]).collect::<Vec<_>>();
+    unsafe {
+        let ptr = CoTaskMemAlloc(wide.len() * 2) as *mut u16;
+        std::ptr::copy_nonoverlapping(wide.as_ptr(), ptr, wide.len());
+        PWSTR(ptr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enums::{DataFlow, DeviceState, Role};
+
+    #[test]
+    fn fake_filters_device_ids() {
+        let source = FakeDeviceSource::sample();
+
+        assert_eq!(
+            source.device_ids(DataFlow::All, DeviceState::All).unwrap(),
+            ["speakers", "headset", "mic"]
+        );
+        assert_eq!(
+            source
+                .device_ids(DataFlow::Render, DeviceState::Active)
+                .unwrap(),
+            ["speakers"]
+        );
+        assert_eq!(
+            source
+                .device_ids(DataFlow::Capture, DeviceState::Unplugged)
+                .unwrap(),
+            Vec::<String>::new()
+        );
+    }
+
+    // The rest make devices or deliver events through COM, which is only linked on Windows (as is
+    // anything using the fake as a `dyn DeviceSource`)
+
+    #[test]
+    fn fake_devices_have_their_scripted_properties() {
+        let source = FakeDeviceSource::sample();
+
+        let mic = source.get_device("MIC").unwrap();
+        assert_eq!(mic.id, "mic");
+        assert_eq!(mic.friendly_name, "Microphone");
+        assert_eq!(mic.data_flow().unwrap(), DataFlow::Capture);
+        assert_eq!(mic.state().unwrap(), DeviceState::Active);
+
+        let default = source
+            .get_default_device(DataFlow::Render.into(), Role::Console.into())
+            .unwrap();
+        assert_eq!(default.friendly_name, "Speakers");
+
+        let unknown = source.get_device("webcam").map(|_| ()).unwrap_err();
+        assert!(matches!(
+            unknown.downcast_ref::<WindowsAudioError>(),
+            Some(WindowsAudioError::WindowsErr(e)) if e.code() == ELEMENT_NOT_FOUND
+        ));
+    }
+
+    #[test]
+    fn scripted_events_reach_registered_clients() {
+        let source = FakeDeviceSource::sample();
+        let (tx, rx) = async_std::channel::bounded(2);
+        let client = NotificationClient::new(tx).unwrap();
+        source.register_notification(&client).unwrap();
+
+        let unplugged = DeviceNotificationEvent::StateChanged("mic".into(), DeviceState::Unplugged);
+        source.notify(&unplugged).unwrap();
+        assert_eq!(rx.try_recv().unwrap().unwrap(), unplugged);
+
+        source.unregister_notification(&client).unwrap();
+        source
+            .notify(&DeviceNotificationEvent::Added("webcam".into()))
+            .unwrap();
+        assert!(rx.try_recv().is_err());
+        assert_eq!(source.client_count(), 0);
+    }
+
+    #[test]
+    fn closing_all_ends_the_clients_streams() {
+        let source = FakeDeviceSource::sample();
+        let (tx, rx) = async_std::channel::bounded(1);
+        source
+            .register_notification(&NotificationClient::new(tx).unwrap())
+            .unwrap();
+
+        source.close_all().unwrap();
+        assert!(rx.is_closed());
+        assert_eq!(source.client_count(), 0);
+    }
+}
@@ -0,0 +1,199 @@
+//! The sample format of a device's shared-mode mix.
+
+use windows::{
+    core::Result,
+    Win32::{
+        Media::{
+            Audio::{IAudioClient, IMMDevice, WAVEFORMATEX, WAVEFORMATEXTENSIBLE, WAVE_FORMAT_PCM},
+            KernelStreaming::{KSDATAFORMAT_SUBTYPE_PCM, WAVE_FORMAT_EXTENSIBLE},
+            Multimedia::{KSDATAFORMAT_SUBTYPE_IEEE_FLOAT, WAVE_FORMAT_IEEE_FLOAT},
+        },
+        System::Com::{CoTaskMemFree, CLSCTX_ALL},
+    },
+};
+
+/// Size of the WAVEFORMATEXTENSIBLE fields that follow the WAVEFORMATEX header
+const EXTENSIBLE_EXTRA_SIZE: u16 =
+    (std::mem::size_of::<WAVEFORMATEXTENSIBLE>() - std::mem::size_of::<WAVEFORMATEX>()) as u16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MixFormat {
+    pub channels: u16,
+    pub sample_rate: u32,
+    /// Size of each sample's container
+    pub bits_per_sample: u16,
+    /// Bits of each sample that hold data, e.g. 24 of a 32-bit container
+    pub valid_bits_per_sample: u16,
+    /// `pcm` (integer samples), `float` or `unknown`
+    pub sample_format: &'static str,
+    /// `SPEAKER_*` mask of the channels, or 0 if the format doesn't say
+    pub channel_mask: u32,
+}
+
+impl MixFormat {
+    /// # Safety
+    ///
+    /// `format` must point to a WAVEFORMATEX followed by the `cbSize` bytes it declares.
+    pub unsafe fn from_waveformat(format: *const WAVEFORMATEX) -> Self {
+        let basic = *format;
+        let tag = basic.wFormatTag as u32;
+
+        if tag == WAVE_FORMAT_EXTENSIBLE && basic.cbSize >= EXTENSIBLE_EXTRA_SIZE {
+            let extensible = *(format as *const WAVEFORMATEXTENSIBLE);
+            let sample_format = match extensible.SubFormat {
+                KSDATAFORMAT_SUBTYPE_PCM => "pcm",
+                KSDATAFORMAT_SUBTYPE_IEEE_FLOAT => "float",
+                _ => "unknown",
+            };
+            return MixFormat {
+                channels: basic.nChannels,
+                sample_rate: basic.nSamplesPerSec,
+                bits_per_sample: basic.wBitsPerSample,
+                valid_bits_per_sample: extensible.Samples.wValidBitsPerSample,
+                sample_format,
+                channel_mask: extensible.dwChannelMask,
+            };
+        }
+
+        MixFormat {
+            channels: basic.nChannels,
+            sample_rate: basic.nSamplesPerSec,
+            bits_per_sample: basic.wBitsPerSample,
+            valid_bits_per_sample: basic.wBitsPerSample,
+            sample_format: match tag {
+                WAVE_FORMAT_PCM => "pcm",
+                WAVE_FORMAT_IEEE_FLOAT => "float",
+                _ => "unknown",
+            },
+            channel_mask: 0,
+        }
+    }
+}
+
+/// Convert little-endian samples in the given format to `f32` in the range -1.0 to 1.0
+///
+/// Integer samples can be 8-bit (unsigned), 16-bit, packed 24-bit or 32-bit; a 24-bit sample in
+/// a 32-bit container is left-justified, so it is converted as 32-bit. Returns None for any other
+/// format, or if `data` isn't a whole number of samples.
+pub fn samples_to_f32(sample_format: &str, bits_per_sample: u16, data: &[u8]) -> Option<Vec<f32>> {
+    let size = bits_per_sample as usize / 8;
+    if size == 0 {
+        return None;
+    }
+    let samples = data.chunks_exact(size);
+    if !samples.remainder().is_empty() {
+        return None;
+    }
+
+    Some(match (sample_format, bits_per_sample) {
+        ("float", 32) => samples
+            .map(|s| f32::from_le_bytes([s[0], s[1], s[2], s[3]]))
+            .collect(),
+        ("pcm", 8) => samples.map(|s| (s[0] as f32 - 128.0) / 128.0).collect(),
+        ("pcm", 16) => samples
+            .map(|s| i16::from_le_bytes([s[0], s[1]]) as f32 / 32768.0)
+            .collect(),
+        // Place the three bytes at the top of an i32 so the sign is extended
+        ("pcm", 24) => samples
+            .map(|s| i32::from_le_bytes([0, s[0], s[1], s[2]]) as f32 / 2_147_483_648.0)
+            .collect(),
+        ("pcm", 32) => samples
+            .map(|s| i32::from_le_bytes([s[0], s[1], s[2], s[3]]) as f32 / 2_147_483_648.0)
+            .collect(),
+        _ => return None,
+    })
+}
+
+pub fn mix_format(device: &IMMDevice) -> Result<MixFormat> {
+    unsafe {
+        let client: IAudioClient = device.Activate(CLSCTX_ALL, None)?;
+        let format = client.GetMixFormat()?;
+        let mix = MixFormat::from_waveformat(format);
+        CoTaskMemFree(Some(format as *const _));
+        Ok(mix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use windows::Win32::Media::Audio::WAVEFORMATEXTENSIBLE_0;
+
+    #[test]
+    fn decodes_extensible_float() {
+        let format = WAVEFORMATEXTENSIBLE {
+            Format: WAVEFORMATEX {
+                wFormatTag: WAVE_FORMAT_EXTENSIBLE as u16,
+                nChannels: 2,
+                nSamplesPerSec: 48000,
+                nAvgBytesPerSec: 384000,
+                nBlockAlign: 8,
+                wBitsPerSample: 32,
+                cbSize: EXTENSIBLE_EXTRA_SIZE,
+            },
+            Samples: WAVEFORMATEXTENSIBLE_0 {
+                wValidBitsPerSample: 32,
+            },
+            dwChannelMask: 3,
+            SubFormat: KSDATAFORMAT_SUBTYPE_IEEE_FLOAT,
+        };
+
+        let mix = unsafe { MixFormat::from_waveformat(&format.Format) };
+
+        assert_eq!(
+            mix,
+            MixFormat {
+                channels: 2,
+                sample_rate: 48000,
+                bits_per_sample: 32,
+                valid_bits_per_sample: 32,
+                sample_format: "float",
+                channel_mask: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn converts_samples_to_f32() {
+        assert_eq!(
+            samples_to_f32("pcm", 16, &[0x00, 0x80, 0x00, 0x00, 0x00, 0x40]).unwrap(),
+            [-1.0, 0.0, 0.5]
+        );
+        assert_eq!(
+            samples_to_f32("pcm", 24, &[0x00, 0x00, 0xc0, 0xff, 0xff, 0x7f]).unwrap(),
+            [-0.5, 8_388_607.0 / 8_388_608.0]
+        );
+        assert_eq!(
+            samples_to_f32("pcm", 32, &(i32::MIN / 4).to_le_bytes()).unwrap(),
+            [-0.25]
+        );
+        assert_eq!(samples_to_f32("pcm", 8, &[0, 128]).unwrap(), [-1.0, 0.0]);
+        assert_eq!(
+            samples_to_f32("float", 32, &0.75f32.to_le_bytes()).unwrap(),
+            [0.75]
+        );
+
+        assert_eq!(samples_to_f32("pcm", 16, &[0, 0, 0]), None);
+        assert_eq!(samples_to_f32("float", 64, &[0; 8]), None);
+        assert_eq!(samples_to_f32("unknown", 16, &[0; 2]), None);
+    }
+
+    #[test]
+    fn decodes_basic_pcm() {
+        let format = WAVEFORMATEX {
+            wFormatTag: WAVE_FORMAT_PCM as u16,
+            nChannels: 1,
+            nSamplesPerSec: 44100,
+            nAvgBytesPerSec: 88200,
+            nBlockAlign: 2,
+            wBitsPerSample: 16,
+            cbSize: 0,
+        };
+
+        let mix = unsafe { MixFormat::from_waveformat(&format) };
+
+        assert_eq!(mix.sample_format, "pcm");
+        assert_eq!(mix.valid_bits_per_sample, 16);
+        assert_eq!(mix.channel_mask, 0);
+    }
+}
@@ -47,6 +47,7 @@ ATTRIBUTES_BLACKLIST = {
 def module_stubs(module) -> ast.Module:
     types_to_import = {"typing"}
     classes = []
+    constants = []
     functions = []
     for (member_name, member_value) in inspect.getmembers(module):
         if member_name.startswith("__"):
@@ -55,12 +56,21 @@ def module_stubs(module) -> ast.Module:
             classes.append(class_stubs(member_name, member_value, types_to_import))
         elif inspect.isbuiltin(member_value):
             functions.append(function_stub(member_name, member_value, types_to_import))
+        elif isinstance(member_value, (bool, int, float, str)):
+            constants.append(
+                ast.AnnAssign(
+                    target=ast.Name(id=member_name, ctx=AST_STORE),
+                    annotation=ast.Name(id=type(member_value).__name__, ctx=AST_LOAD),
+                    simple=1,
+                )
+            )
         else:
             logging.warning(f"Unsupported root construction {member_name}")
     return ast.Module(
         body=[ast.ImportFrom(module="__future__", names=[ast.alias(name="annotations")])]
         + [ast.Import(names=[ast.alias(name=t)]) for t in sorted(types_to_import)]
         + classes
+        + constants
         + functions,
         type_ignores=[],
     )
@@ -1,23 +1,44 @@
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use anyhow::{Context, Result};
-use async_std::channel::{bounded, Receiver, RecvError};
+use anyhow::Context;
+use async_std::channel::{bounded, Receiver, RecvError, Sender};
+use async_std::{future, task};
 use collection::NotificationClient;
-use errors::WindowsAudioError;
+use errors::{into_pyerr, Result, WindowsAudioError};
+use log::{debug, warn};
 use pyo3::exceptions::PyIndexError;
 use pyo3::exceptions::PyKeyError;
+use pyo3::exceptions::PyOSError;
+use pyo3::exceptions::PyPermissionError;
+use pyo3::exceptions::PyValueError;
 use pyo3::pyclass::CompareOp;
+use pyo3::types::PyBytes;
 use pyo3::types::PyDict;
 use pyo3::types::PyTuple;
 use pyo3::{exceptions::PyStopAsyncIteration, prelude::*};
+use source::DeviceSource;
+use windows::core::{IUnknown, Vtable};
+use windows::Win32::Foundation::E_ACCESSDENIED;
 use windows::Win32::Media::Audio::IMMNotificationClient;
+use windows::Win32::UI::Shell::PropertiesSystem::PROPERTYKEY;
 
 mod collection;
 mod com;
 mod device;
+mod drain;
+mod ducking;
 mod enums;
 mod errors;
+#[cfg(all(test, windows))]
+mod fake_source;
+mod format;
+mod listeners;
 mod policy_config;
+mod property;
+mod session;
+mod source;
 
 const ELEMENT_NOT_FOUND: windows::core::HRESULT = windows::core::HRESULT(-2147023728i32); // 0x80070490 as i32
 const PARAMETER_INCORRECT: windows::core::HRESULT = windows::core::HRESULT(-2147024809i32); // 0x80070057 as i32
@@ -33,10 +54,26 @@ pub struct PyVolumeChangeEvent {
     #[pyo3(get)]
     pub mute: bool,
 
+    /// The master volume, as a scalar from 0.0 to 1.0 unless the events were requested in dB
+    ///
     /// :rtype: float
     #[pyo3(get)]
     pub volume: f32,
 
+    /// The master volume in dB, as the device maps ``volume``, if the events were requested with
+    /// ``with_db`` (or in dB)
+    ///
+    /// :rtype: float | None
+    #[pyo3(get)]
+    pub volume_db: Option<f32>,
+
+    /// The context GUID the change was made with (e.g. by ``AudioDevice.set_mute``), or None if
+    /// it was made without one
+    ///
+    /// :rtype: str | None
+    #[pyo3(get)]
+    pub context: Option<String>,
+
     channel_volumes: Box<[f32]>,
 }
 
@@ -48,11 +85,53 @@ impl PyVolumeChangeEvent {
         PyTuple::new(py, self.channel_volumes.iter())
     }
 
+    /// Number of channels the device has, i.e. ``len(channel_volumes)``
+    ///
+    /// :rtype: int
+    #[getter]
+    fn channel_count(&self) -> usize {
+        self.channel_volumes.len()
+    }
+
+    /// Left/right balance from -1.0 (fully left) to 1.0 (fully right), or None if the device
+    /// isn't stereo
+    ///
+    /// :rtype: float | None
+    #[getter]
+    fn balance(&self) -> Option<f32> {
+        device::stereo_balance(&self.channel_volumes)
+    }
+
+    /// Whether the event came from an output (RENDER) or input (CAPTURE) device
+    ///
+    /// :rtype: DataFlow
+    #[getter]
+    fn data_flow(&self, py: Python) -> Result<enums::DataFlow> {
+        Ok(self.device.borrow(py).0.data_flow()?)
+    }
+
+    /// The event as a plain dict, e.g. for logging or serializing
+    ///
+    /// :rtype: dict[str, typing.Any]
+    #[pyo3(text_signature = "($self)")]
+    fn to_dict<'a>(&self, py: Python<'a>) -> PyResult<&'a PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("device_id", &self.device.borrow(py).0.id)?;
+        dict.set_item("data_flow", self.data_flow(py)?.into_py(py))?;
+        dict.set_item("mute", self.mute)?;
+        dict.set_item("volume", self.volume)?;
+        dict.set_item("volume_db", self.volume_db)?;
+        dict.set_item("channel_volumes", self.get_channel_volumes(py))?;
+        dict.set_item("context", &self.context)?;
+        Ok(dict)
+    }
+
     fn __repr__(&self, py: Python) -> PyResult<String> {
         let device = self.device.as_ref(py);
         Ok(format!(
-            "<VolumChangeEvent device={} mute={} volume={} channel_volumes={:?}",
+            "<VolumChangeEvent device={} data_flow={} mute={} volume={} channel_volumes={:?}",
             device.repr()?,
+            self.data_flow(py)?.py_name(),
             self.mute,
             self.volume,
             self.channel_volumes,
@@ -66,13 +145,15 @@ impl PyVolumeChangeEvent {
             device,
             mute: e.mute,
             volume: e.volume,
+            volume_db: e.volume_db,
             channel_volumes: e.channel_volumes,
+            context: e.context.as_ref().map(property::format_guid),
         }
     }
 }
 
 #[pyclass]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 #[allow(non_camel_case_types)]
 enum DeviceCollectionEventType {
     #[pyo3(name = "STATE_CHANGED")]
@@ -83,6 +164,8 @@ enum DeviceCollectionEventType {
     Removed,
     #[pyo3(name = "DEFAULT_CHANGED")]
     DefaultChanged,
+    #[pyo3(name = "PROPERTY_VALUE_CHANGED")]
+    PropertyValueChanged,
 }
 
 #[pyclass(name = "DeviceCollectionEvent")]
@@ -111,6 +194,31 @@ struct PyDeviceCollectionEvent {
     /// :rtype: Role | None
     #[pyo3(get)]
     role: Option<enums::Role>,
+
+    /// The changed property, as a (fmtid, pid) key like ``AudioDevice.get_property`` takes.
+    ///
+    /// Only valid for PROPERTY_VALUE_CHANGED events
+    ///
+    /// :rtype: tuple[str, int] | None
+    #[pyo3(get)]
+    property_key: Option<(String, u32)>,
+
+    /// The new default device.
+    ///
+    /// Only set for DEFAULT_CHANGED events from ``DeviceCollection.collection_events(resolve_defaults=True)``
+    ///
+    /// :rtype: AudioDevice | None
+    #[pyo3(get)]
+    device: Option<Py<PyAudioDevice>>,
+
+    /// The friendly name of the device, looked up as the event arrived (or, for REMOVED events,
+    /// remembered from before).
+    ///
+    /// Only set for events from ``DeviceCollection.collection_events(include_metadata=True)``
+    ///
+    /// :rtype: str | None
+    #[pyo3(get)]
+    name: Option<String>,
 }
 
 #[pymethods]
@@ -137,6 +245,12 @@ impl PyDeviceCollectionEvent {
             let s = pyobj.as_ref(py).repr()?;
             repr.push_str(&format!(" role={}", s));
         }
+        if let Some((fmtid, pid)) = self.property_key.as_ref() {
+            repr.push_str(&format!(" property_key=('{}', {})", fmtid, pid));
+        }
+        if let Some(name) = self.name.as_ref() {
+            repr.push_str(&format!(" name='{}'", name));
+        }
         repr.push('>');
 
         Ok(repr)
@@ -153,6 +267,9 @@ impl From<collection::DeviceNotificationEvent> for PyDeviceCollectionEvent {
                     state: Some(state),
                     dataflow: None,
                     role: None,
+                    property_key: None,
+                    device: None,
+                    name: None,
                 }
             }
 
@@ -163,6 +280,9 @@ impl From<collection::DeviceNotificationEvent> for PyDeviceCollectionEvent {
                     state: None,
                     dataflow: Some(flow),
                     role: Some(role),
+                    property_key: None,
+                    device: None,
+                    name: None,
                 }
             }
 
@@ -172,38 +292,77 @@ impl From<collection::DeviceNotificationEvent> for PyDeviceCollectionEvent {
                 state: None,
                 dataflow: None,
                 role: None,
+                property_key: None,
+                device: None,
+                name: None,
             },
 
+            collection::DeviceNotificationEvent::PropertyValueChanged(device_id, key) => {
+                PyDeviceCollectionEvent {
+                    kind: DeviceCollectionEventType::PropertyValueChanged,
+                    device_id,
+                    state: None,
+                    dataflow: None,
+                    role: None,
+                    property_key: Some((property::format_guid(&key.fmtid), key.pid)),
+                    device: None,
+                    name: None,
+                }
+            }
+
             collection::DeviceNotificationEvent::Removed(device_id) => PyDeviceCollectionEvent {
                 kind: DeviceCollectionEventType::Removed,
                 device_id,
                 state: None,
                 dataflow: None,
                 role: None,
+                property_key: None,
+                device: None,
+                name: None,
             },
         }
     }
 }
 
 #[pyclass(module = "windows_audio_control")]
-struct DevicesDict(Arc<collection::DeviceEnumerator>);
+struct DevicesDict(Arc<dyn DeviceSource>, enums::DataFlow);
 
 #[pymethods]
 impl DevicesDict {
-    pub fn __getitem__(&self, key: &str) -> PyResult<PyAudioDevice> {
+    pub fn __getitem__(&self, key: &str) -> Result<PyAudioDevice> {
         match self.0.get_device(key) {
-            Ok(dev) => Ok(PyAudioDevice(dev)),
+            Ok(dev) => {
+                if self.1 != enums::DataFlow::All && dev.data_flow()? != self.1 {
+                    return Err(PyKeyError::new_err(format!("unknown device id {:?}", key)).into());
+                }
+                Ok(PyAudioDevice(dev))
+            }
             Err(err) => {
                 match err.downcast_ref::<WindowsAudioError>() {
                     // Handle 0x80070057 specially ("The parameter is incorrect.")
                     Some(WindowsAudioError::WindowsErr(e)) if e.code() == PARAMETER_INCORRECT => {
-                        Err(PyKeyError::new_err(format!("unknown device id {:?}", key)))
+                        Err(PyKeyError::new_err(format!("unknown device id {:?}", key)).into())
                     }
                     _ => Err(err.into()),
                 }
             }
         }
     }
+
+    pub fn __len__(&self) -> Result<usize> {
+        Ok(self
+            .0
+            .get_collection(self.1, enums::DeviceState::All)?
+            .length()? as usize)
+    }
+
+    /// Ids of all the devices in this dict
+    ///
+    /// :rtype: list[str]
+    #[pyo3(text_signature = "($self)")]
+    pub fn keys(&self) -> Result<Vec<String>> {
+        Ok(self.0.device_ids(self.1, enums::DeviceState::All)?)
+    }
 }
 
 #[pyclass(module = "windows_audio_control", subclass)]
@@ -215,27 +374,90 @@ impl FilteredDeviceCollection {
         Ok(self.0.length()? as usize)
     }
 
-    pub fn __getitem__(&self, idx: usize) -> PyResult<PyAudioDevice> {
+    pub fn __getitem__(&self, idx: usize) -> Result<PyAudioDevice> {
         if idx >= self.0.length()? as usize {
-            return Err(PyIndexError::new_err("device index out of range"));
+            return Err(PyIndexError::new_err("device index out of range").into());
         }
         let dev = self.0.get(idx as u32)?;
         Ok(PyAudioDevice(dev))
     }
 }
 
+/// Windows only has default devices per data flow, so rather than passing ALL on to it (and
+/// getting back "The parameter is incorrect"), say what's wrong
+fn check_default_flow(dataflow: enums::DataFlow) -> PyResult<()> {
+    match dataflow {
+        enums::DataFlow::All => Err(PyValueError::new_err(
+            "default device requires RENDER or CAPTURE",
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Whether `err` is because the device has gone (been removed) since it was notified about, rather
+/// than something being wrong
+fn device_gone(err: &anyhow::Error) -> bool {
+    let code = match err.downcast_ref::<WindowsAudioError>() {
+        Some(WindowsAudioError::WindowsErr(e)) => e.code(),
+        _ => match err.downcast_ref::<windows::core::Error>() {
+            Some(e) => e.code(),
+            None => return false,
+        },
+    };
+    code == ELEMENT_NOT_FOUND
+}
+
 #[pyclass(module = "windows_audio_control", name = "DeviceCollection", subclass)]
-struct PyDeviceCollection(Arc<collection::DeviceEnumerator>);
+struct PyDeviceCollection(Arc<dyn DeviceSource>);
 
 impl PyDeviceCollection {
-    fn _get_default_device(&self, direction: enums::DataFlow) -> PyResult<PyAudioDevice> {
-        match self.0.get_default_device(direction.into()) {
+    fn _mute_all(&self, dataflow: enums::DataFlow, muted: bool) -> Result<BatchResult> {
+        let devices = self
+            .0
+            .get_collection(dataflow, enums::DeviceState::Active)?;
+
+        let mut outcome = BatchResult::default();
+        for idx in 0..devices.length()? {
+            match devices.get(idx) {
+                Ok(device) => outcome.push(device.id.clone(), device.set_mute(muted)),
+                Err(err) => outcome.push(format!("#{}", idx), Err(err)),
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// The current state of the default (console) device of `direction`, if there is one
+    fn _default_state(
+        &self,
+        py: Python,
+        direction: enums::DataFlow,
+    ) -> Result<Option<Py<PyVolumeChangeEvent>>> {
+        let device = match self._get_default_device(direction, enums::Role::Console) {
+            Ok(device) => device,
+            Err(err) if err.is_instance_of::<PyKeyError>(py) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let state = device.0.volume_state()?;
+        let event = PyVolumeChangeEvent::new(Py::new(py, device)?, state);
+        Ok(Some(Py::new(py, event)?))
+    }
+
+    fn _get_default_device(
+        &self,
+        direction: enums::DataFlow,
+        role: enums::Role,
+    ) -> PyResult<PyAudioDevice> {
+        check_default_flow(direction)?;
+        match self.0.get_default_device(direction.into(), role.into()) {
             Ok(dev) => Ok(PyAudioDevice(dev)),
             Err(err) => match err.downcast_ref::<WindowsAudioError>() {
-                Some(WindowsAudioError::WindowsErr(e)) if e.code() == ELEMENT_NOT_FOUND => Err(
-                    PyKeyError::new_err(format!("No default device of type {:?} found", direction)),
-                ),
-                _ => Err(err.into()),
+                Some(WindowsAudioError::WindowsErr(e)) if e.code() == ELEMENT_NOT_FOUND => {
+                    Err(PyKeyError::new_err(format!(
+                        "No default device of type {:?} found for role {:?}",
+                        direction, role
+                    )))
+                }
+                _ => Err(into_pyerr(err)),
             },
         }
     }
@@ -254,7 +476,120 @@ impl PyDeviceCollection {
     /// :rtype: dict[str, AudioDevice]
     #[getter]
     pub fn devices(&self) -> DevicesDict {
-        DevicesDict(self.0.clone())
+        DevicesDict(self.0.clone(), enums::DataFlow::All)
+    }
+
+    /// Get output devices keyed by device id
+    ///
+    /// :rtype: dict[str, AudioDevice]
+    #[getter]
+    pub fn output_devices(&self) -> DevicesDict {
+        DevicesDict(self.0.clone(), enums::DataFlow::Render)
+    }
+
+    /// Get input devices keyed by device id
+    ///
+    /// :rtype: dict[str, AudioDevice]
+    #[getter]
+    pub fn input_devices(&self) -> DevicesDict {
+        DevicesDict(self.0.clone(), enums::DataFlow::Capture)
+    }
+
+    /// Get the devices of both data flows (input and output) in a single list
+    ///
+    /// :type state_mask: DeviceState
+    /// :rtype: list[AudioDevice]
+    #[pyo3(text_signature = "($self, state_mask = None)")]
+    pub fn all_devices(
+        &self,
+        state_mask: Option<enums::DeviceState>,
+    ) -> Result<Vec<PyAudioDevice>> {
+        let devices = self.0.get_collection(
+            enums::DataFlow::All,
+            state_mask.unwrap_or(enums::DeviceState::All),
+        )?;
+        (0..devices.length()?)
+            .map(|idx| Ok(PyAudioDevice(devices.get(idx)?)))
+            .collect()
+    }
+
+    /// Get the input devices that something is currently recording from
+    ///
+    /// These are the active input devices with at least one active application audio session,
+    /// e.g. for a "microphone in use" indicator.
+    ///
+    /// :rtype: list[AudioDevice]
+    #[pyo3(text_signature = "($self)")]
+    pub fn devices_capturing(&self) -> Result<Vec<PyAudioDevice>> {
+        let devices = self
+            .0
+            .get_collection(enums::DataFlow::Capture, enums::DeviceState::Active)?;
+
+        let mut capturing = Vec::new();
+        for idx in 0..devices.length()? {
+            let device = devices.get(idx)?;
+            if device.active_session_count()? > 0 {
+                capturing.push(PyAudioDevice(device));
+            }
+        }
+        Ok(capturing)
+    }
+
+    /// Get the devices that aren't working, for troubleshooting: those that are disabled, not
+    /// present or unplugged
+    ///
+    /// Returns ``(device, state)`` pairs, covering both data flows.
+    ///
+    /// :rtype: list[tuple[AudioDevice, DeviceState]]
+    #[pyo3(text_signature = "($self)")]
+    pub fn problem_devices(&self) -> Result<Vec<(PyAudioDevice, enums::DeviceState)>> {
+        let devices = self
+            .0
+            .get_collection(enums::DataFlow::All, enums::DeviceState::All)?;
+
+        let mut problems = Vec::new();
+        for idx in 0..devices.length()? {
+            let device = devices.get(idx)?;
+            let state = device.state()?;
+            if state != enums::DeviceState::Active {
+                problems.push((PyAudioDevice(device), state));
+            }
+        }
+        Ok(problems)
+    }
+
+    /// Get the active devices of the given data flow, for showing in a device picker
+    ///
+    /// The current default device (for the console role) comes first, then the rest in order of
+    /// name.
+    ///
+    /// :type dataflow: DataFlow
+    /// :rtype: list[AudioDevice]
+    #[pyo3(text_signature = "($self, dataflow)")]
+    pub fn devices_ordered(&self, dataflow: enums::DataFlow) -> Result<Vec<PyAudioDevice>> {
+        let default_id = match dataflow {
+            // There is no single default across both flows
+            enums::DataFlow::All => None,
+            _ => self
+                .0
+                .get_default_device_id(dataflow.into(), enums::Role::Console.into())?,
+        };
+
+        let devices = self
+            .0
+            .get_collection(dataflow, enums::DeviceState::Active)?;
+        let mut devices = (0..devices.length()?)
+            .map(|idx| Ok(PyAudioDevice(devices.get(idx)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        devices.sort_by(|a, b| {
+            let a_default = Some(&a.0.id) == default_id.as_ref();
+            let b_default = Some(&b.0.id) == default_id.as_ref();
+            b_default
+                .cmp(&a_default)
+                .then_with(|| a.0.friendly_name.cmp(&b.0.friendly_name))
+        });
+        Ok(devices)
     }
 
     /// Get a collection of devices matching the given parameters
@@ -274,197 +609,2408 @@ impl PyDeviceCollection {
         Ok(FilteredDeviceCollection(Arc::new(c)))
     }
 
-    /// :rtype: AudioDevice
+    /// Find the device referred to by a token from ``AudioDevice.to_token``
     ///
-    /// Get the current default output device (aka speakers)
-    #[pyo3(text_signature = "($self)")]
-    pub fn get_default_output_device(&self) -> PyResult<PyAudioDevice> {
-        self._get_default_device(enums::DataFlow::Render)
+    /// The device is looked up by id first. If that device is no longer active, an active device
+    /// with the same name (and data flow) is returned instead.
+    ///
+    /// :type token: dict[str, str]
+    /// :rtype: AudioDevice
+    #[pyo3(name = "from_token", text_signature = "($self, token)")]
+    pub fn device_from_token(&self, token: &PyDict) -> Result<PyAudioDevice> {
+        let id: Option<String> = token.get_item("id").map(|v| v.extract()).transpose()?;
+        let name: Option<String> = token.get_item("name").map(|v| v.extract()).transpose()?;
+        let dataflow = match token.get_item("data_flow") {
+            Some(v) => {
+                let flow_name: &str = v.extract()?;
+                enums::DataFlow::from_py_name(flow_name).ok_or_else(|| {
+                    PyValueError::new_err(format!("unknown data_flow {:?}", flow_name))
+                })?
+            }
+            None => enums::DataFlow::All,
+        };
+
+        let by_id = match id.and_then(|id| self.0.get_device(&id).ok()) {
+            Some(dev) if dev.state()? == enums::DeviceState::Active => {
+                return Ok(PyAudioDevice(dev))
+            }
+            other => other,
+        };
+
+        if let Some(name) = name.as_ref() {
+            let devices = self
+                .0
+                .get_collection(dataflow, enums::DeviceState::Active)?;
+            for idx in 0..devices.length()? {
+                let dev = devices.get(idx)?;
+                if &dev.friendly_name == name {
+                    return Ok(PyAudioDevice(dev));
+                }
+            }
+        }
+
+        match by_id {
+            Some(dev) => Ok(PyAudioDevice(dev)),
+            None => Err(PyKeyError::new_err(format!(
+                "no device found for token {}",
+                token.repr()?
+            ))
+            .into()),
+        }
     }
 
-    /// Get the current default input device (aka microphone)
+    /// Get the active device with the given friendly name
     ///
+    /// Names are compared exactly. Raises KeyError if no active device of `dataflow` (by default
+    /// either) has the name, and ValueError if more than one does, as then only the device id can
+    /// tell them apart (the error lists their ids). Use ``get_devices_by_name`` to get them all.
+    ///
+    /// :type name: str
+    /// :type dataflow: DataFlow | None
     /// :rtype: AudioDevice
-    #[pyo3(text_signature = "($self)")]
-    pub fn get_default_input_device(&self) -> PyResult<PyAudioDevice> {
-        self._get_default_device(enums::DataFlow::Capture)
+    #[pyo3(text_signature = "($self, name, dataflow = None)")]
+    pub fn get_device_by_name(
+        &self,
+        name: &str,
+        dataflow: Option<enums::DataFlow>,
+    ) -> PyResult<PyAudioDevice> {
+        let mut found = self.get_devices_by_name(name, dataflow)?;
+
+        match found.len() {
+            0 => Err(PyKeyError::new_err(format!(
+                "no active device named {:?}",
+                name
+            ))),
+            1 => Ok(found.remove(0)),
+            n => Err(PyValueError::new_err(format!(
+                "ambiguous name: {} active devices are named {:?}, use one of their ids instead: {}",
+                n,
+                name,
+                found
+                    .iter()
+                    .map(|dev| dev.0.id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
     }
 
-    /// :rtype: CollectionEventsIterator
+    /// Get every active device with the given friendly name, such as two identical USB headsets
     ///
-    ///Asyncronoysly yield the events for this collection (device added or reomved, default changed, etc)
-    #[getter]
-    pub fn events(slf: Py<Self>, py: Python<'_>) -> Result<CollectionEventsIterator> {
-        let (tx, rx) = bounded(1);
-
-        let source = NotificationClient::new(tx)?;
-
-        slf.borrow_mut(py).0.register_notification(&source)?;
-
-        Ok(CollectionEventsIterator {
-            collection: slf,
-            source: Some(source),
-            rx,
-        })
+    /// Names are compared exactly, and the devices are sorted by id, so the order is the same
+    /// from one call to the next. The list is empty if no active device of `dataflow` (by default
+    /// either) has the name.
+    ///
+    /// :type name: str
+    /// :type dataflow: DataFlow | None
+    /// :rtype: list[AudioDevice]
+    #[pyo3(text_signature = "($self, name, dataflow = None)")]
+    pub fn get_devices_by_name(
+        &self,
+        name: &str,
+        dataflow: Option<enums::DataFlow>,
+    ) -> Result<Vec<PyAudioDevice>> {
+        let devices = self.0.get_collection(
+            dataflow.unwrap_or(enums::DataFlow::All),
+            enums::DeviceState::Active,
+        )?;
+        let mut found = Vec::new();
+        for idx in 0..devices.length()? {
+            let dev = devices.get(idx)?;
+            if dev.friendly_name == name {
+                found.push(PyAudioDevice(dev));
+            }
+        }
+        found.sort_by(|a, b| a.0.id.cmp(&b.0.id));
+        Ok(found)
     }
-}
-
-#[pyclass(module = "windows_audio_control", subclass, unsendable)]
-/// Async iterator of changes to devices in a collection
-struct CollectionEventsIterator {
-    // Keep the collection alive as long as the iterator is
-    collection: Py<PyDeviceCollection>,
-    source: Option<IMMNotificationClient>,
-    rx: Receiver<anyhow::Result<collection::DeviceNotificationEvent>>,
-}
 
-impl CollectionEventsIterator {
-    fn _next_event<'a>(&'a mut self, py: Python<'a>) -> PyResult<&'a PyAny> {
-        let rx = self.rx.clone();
-        pyo3_asyncio::async_std::future_into_py(py, async move {
-            match rx.recv().await {
-                Ok(val) => {
-                    let event = val?;
-                    let pyevent: PyDeviceCollectionEvent = event.into();
+    /// Make the output and/or input devices with the given friendly names the default for every
+    /// role
+    ///
+    /// Both names are looked up (as by ``get_device_by_name``) before either default is changed,
+    /// so a missing or ambiguous name leaves the defaults as they were.
+    ///
+    /// :type output_name: str | None
+    /// :type input_name: str | None
+    #[pyo3(text_signature = "($self, output_name = None, input_name = None)")]
+    pub fn set_defaults_by_name(
+        &self,
+        output_name: Option<&str>,
+        input_name: Option<&str>,
+    ) -> PyResult<()> {
+        let mut devices = Vec::new();
+        if let Some(name) = output_name {
+            devices.push(self.get_device_by_name(name, Some(enums::DataFlow::Render))?);
+        }
+        if let Some(name) = input_name {
+            devices.push(self.get_device_by_name(name, Some(enums::DataFlow::Capture))?);
+        }
 
-                    Ok(Python::with_gil(|py| pyevent.into_py(py)))
-                }
-                Err(RecvError) => Err(PyStopAsyncIteration::new_err("device enumerator closed")),
+        for device in devices {
+            for role in enums::Role::ALL {
+                device.set_default(role)?;
             }
-        })
-    }
-}
-
-#[pymethods]
-impl CollectionEventsIterator {
-    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
-        slf
+        }
+        Ok(())
     }
 
-    /// :rtype: DeviceCollectionEvent
+    /// Make the devices with the given ids the default output and/or input device, for every role
     ///
-    pub fn __anext__<'a>(&'a mut self, py: Python<'a>) -> PyResult<Option<&'a PyAny>> {
-        match self._next_event(py) {
-            Ok(event) => Ok(Some(event)),
-            Err(err) => Err(err),
+    /// A device or role that can't be made the default doesn't stop the others. Returns a
+    /// ``(target, ok, error_message)`` tuple for each device and role, where `target` is the
+    /// device id and role name, like ``"{0.0.0.00000000}.{...} CONSOLE"``.
+    ///
+    /// :type output_id: str | None
+    /// :type input_id: str | None
+    /// :rtype: list[tuple[str, bool, typing.Optional[str]]]
+    #[pyo3(text_signature = "($self, output_id = None, input_id = None)")]
+    pub fn set_default_devices(
+        &self,
+        output_id: Option<&str>,
+        input_id: Option<&str>,
+    ) -> BatchResult {
+        let mut outcome = BatchResult::default();
+        for device_id in output_id.into_iter().chain(input_id) {
+            for role in enums::Role::ALL {
+                outcome.push(
+                    format!("{} {}", device_id, role.py_name()),
+                    device::set_default_endpoint(device_id, role.into()),
+                );
+            }
         }
+        outcome
     }
 
-    /// Close the iterator
-    #[pyo3(text_signature = "($self)")]
-    pub fn close(&mut self, py: Python) -> Result<()> {
-        if let Some(source) = self.source.as_ref() {
-            let obj = self.collection.borrow(py);
-            let collection = obj.0.as_ref();
-            collection
-                .unregister_notification(source)
-                .context("Unable to close CollectionEventsIterator")?;
-            self.source = None
+    /// Get the devices that are part of the physical device with the given container id
+    ///
+    /// :type container_id: str
+    /// :type state_mask: DeviceState
+    /// :rtype: list[AudioDevice]
+    #[pyo3(text_signature = "($self, container_id, state_mask = None)")]
+    pub fn devices_in_container(
+        &self,
+        container_id: &str,
+        state_mask: Option<enums::DeviceState>,
+    ) -> Result<Vec<PyAudioDevice>> {
+        let container_id =
+            property::parse_guid(container_id).map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+        let devices = self.0.get_collection(
+            enums::DataFlow::All,
+            state_mask.unwrap_or(enums::DeviceState::Active),
+        )?;
+        let mut found = Vec::new();
+        for idx in 0..devices.length()? {
+            let dev = devices.get(idx)?;
+            if dev.container_id()? == Some(container_id) {
+                found.push(PyAudioDevice(dev));
+            }
         }
-        Ok(())
+        Ok(found)
     }
-}
 
-impl Drop for CollectionEventsIterator {
-    fn drop(&mut self) {
-        _ = Python::with_gil(|py| self.close(py));
+    /// Get the device a DeviceCollectionEvent is about, e.g. to start listening to the volume of
+    /// a newly added device
+    ///
+    /// Raises KeyError if the device no longer exists.
+    ///
+    /// :type event: DeviceCollectionEvent
+    /// :rtype: AudioDevice
+    #[pyo3(text_signature = "($self, event)")]
+    pub fn device_for_event(&self, event: &PyDeviceCollectionEvent) -> Result<PyAudioDevice> {
+        self.devices().__getitem__(&event.device_id)
     }
-}
 
-#[pyclass(module = "windows_audio_control", subclass, unsendable)]
-/// Async iterator of changes to a device's volume
-struct AudioDeviceEventIterator {
-    // Keep the device alive so we can use it in `repr`, but don't create a _rust_ memory cycle
-    #[pyo3(get)]
-    device: Py<PyAudioDevice>,
-    rx: Receiver<device::VolumeChangeEvent>,
-}
+    /// Compare the current devices with the ids from an earlier enumeration, such as
+    /// ``list(collection.devices.keys())``
+    ///
+    /// Returns ``(added, removed)``: the devices (of any state) that aren't in `previous_ids`, and
+    /// the ids from `previous_ids` whose devices no longer exist. Ids are compared after
+    /// ``normalize_device_id``.
+    ///
+    /// :type previous_ids: list[str]
+    /// :rtype: tuple[list[AudioDevice], list[str]]
+    #[pyo3(text_signature = "($self, previous_ids)")]
+    pub fn diff(&self, previous_ids: Vec<String>) -> Result<(Vec<PyAudioDevice>, Vec<String>)> {
+        let (added, removed) = source::diff_device_ids(self.0.as_ref(), &previous_ids)?;
+        let added = added
+            .iter()
+            .map(|id| Ok(PyAudioDevice(self.0.get_device(id)?)))
+            .collect::<Result<_>>()?;
+        Ok((added, removed))
+    }
 
-impl AudioDeviceEventIterator {
-    pub fn _next_event<'a>(&'a mut self, py: Python<'a>) -> PyResult<&'a PyAny> {
-        let rx = self.rx.clone();
-        let device = self.device.clone();
-        pyo3_asyncio::async_std::future_into_py(py, async move {
-            match rx.recv().await {
-                Ok(val) => {
-                    let pyevent = PyVolumeChangeEvent::new(device, val);
-                    Ok(Python::with_gil(|py| pyevent.into_py(py)))
-                }
-                Err(RecvError) => Err(PyStopAsyncIteration::new_err("audio session closed")),
-            }
-        })
+    /// Get the device an AudioSession plays to or records from, looked up afresh by its id
+    ///
+    /// Raises KeyError if the device no longer exists.
+    ///
+    /// :type session: AudioSession
+    /// :rtype: AudioDevice
+    #[pyo3(text_signature = "($self, session)")]
+    pub fn device_for_session(
+        &self,
+        py: Python,
+        session: &PyAudioSession,
+    ) -> Result<PyAudioDevice> {
+        self.devices().__getitem__(&session.device_id(py))
     }
-}
 
-#[pymethods]
-impl AudioDeviceEventIterator {
-    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
-        slf
+    /// Get the current default device of the given data flow for the given role
+    ///
+    /// The data flow must be RENDER or CAPTURE; ALL raises ValueError.
+    ///
+    /// :type dataflow: DataFlow
+    /// :type role: Role
+    /// :rtype: AudioDevice
+    #[pyo3(text_signature = "($self, dataflow, role)")]
+    pub fn get_default_device(
+        &self,
+        dataflow: enums::DataFlow,
+        role: enums::Role,
+    ) -> PyResult<PyAudioDevice> {
+        self._get_default_device(dataflow, role)
+    }
+
+    /// Make the device with the given id the default for the specified role
+    ///
+    /// :type device_id: str
+    /// :type role: Role
+    #[pyo3(text_signature = "($self, device_id, role)")]
+    pub fn set_default_by_id(&self, device_id: &str, role: enums::Role) -> PyResult<()> {
+        if device_id.is_empty() {
+            return Err(PyKeyError::new_err(format!(
+                "unknown device id {:?}",
+                device_id
+            )));
+        }
+        match device::set_default_endpoint(device_id, role.into()) {
+            Ok(()) => Ok(()),
+            // Handle 0x80070057 specially ("The parameter is incorrect.")
+            Err(e) if e.code() == PARAMETER_INCORRECT => Err(PyKeyError::new_err(format!(
+                "unknown device id {:?}",
+                device_id
+            ))),
+            Err(e) => Err(WindowsAudioError::from(e).into()),
+        }
+    }
+
+    /// What Windows does to other sounds when a communications session (e.g. a call) is active
+    ///
+    /// This is the "Communications" setting of the Sound control panel. It can only be read:
+    /// Windows offers no supported way to change it.
+    ///
+    /// :rtype: DuckingPreference
+    #[getter]
+    pub fn ducking_preference(&self) -> Result<enums::DuckingPreference> {
+        Ok(ducking::ducking_preference()?)
+    }
+
+    /// Get an opaque token for the current default device of the given data flow and role
+    ///
+    /// The token changes whenever the default device does, so it can be polled and compared to
+    /// spot changes without subscribing to ``events``. It is None if there is no such device.
+    /// The data flow must be RENDER or CAPTURE; ALL raises ValueError.
+    ///
+    /// :type dataflow: DataFlow
+    /// :type role: Role
+    /// :rtype: str | None
+    #[pyo3(text_signature = "($self, dataflow, role)")]
+    pub fn current_default_token(
+        &self,
+        dataflow: enums::DataFlow,
+        role: enums::Role,
+    ) -> Result<Option<String>> {
+        check_default_flow(dataflow)?;
+        Ok(self.0.get_default_device_id(dataflow.into(), role.into())?)
+    }
+
+    /// :rtype: AudioDevice
+    ///
+    /// Get the current default output device (aka speakers)
+    #[pyo3(text_signature = "($self)")]
+    pub fn get_default_output_device(&self) -> PyResult<PyAudioDevice> {
+        self._get_default_device(enums::DataFlow::Render, enums::Role::Console)
+    }
+
+    /// Get the current default input device (aka microphone)
+    ///
+    /// :rtype: AudioDevice
+    #[pyo3(text_signature = "($self)")]
+    pub fn get_default_input_device(&self) -> PyResult<PyAudioDevice> {
+        self._get_default_device(enums::DataFlow::Capture, enums::Role::Console)
+    }
+
+    /// Get the default output and input devices along with their current volume and mute, in one
+    /// call
+    ///
+    /// :rtype: DefaultSummary
+    #[pyo3(text_signature = "($self)")]
+    pub fn default_summary(&self, py: Python) -> Result<DefaultSummary> {
+        Ok(DefaultSummary {
+            output: self._default_state(py, enums::DataFlow::Render)?,
+            input: self._default_state(py, enums::DataFlow::Capture)?,
+        })
+    }
+
+    /// Get the state of the whole system's audio in one call: the default output and input
+    /// devices with their volume, the output's peak level, and how many devices are active
+    ///
+    /// Missing default devices are reported as None rather than raising.
+    ///
+    /// :rtype: SystemAudioState
+    #[pyo3(text_signature = "($self)")]
+    pub fn snapshot_state(&self, py: Python) -> Result<SystemAudioState> {
+        let output = self._default_state(py, enums::DataFlow::Render)?;
+        let output_peak = output.as_ref().and_then(|event| {
+            let device = event.borrow(py).device.clone_ref(py);
+            let peak = device
+                .borrow(py)
+                .0
+                .open_meter()
+                .and_then(|m| m.peak_value());
+            peak.ok()
+        });
+
+        Ok(SystemAudioState {
+            output,
+            output_peak,
+            input: self._default_state(py, enums::DataFlow::Capture)?,
+            active_outputs: self
+                .0
+                .device_ids(enums::DataFlow::Render, enums::DeviceState::Active)?
+                .len(),
+            active_inputs: self
+                .0
+                .device_ids(enums::DataFlow::Capture, enums::DeviceState::Active)?
+                .len(),
+        })
+    }
+
+    /// Get the current default output device for the multimedia role (music, movies etc)
+    ///
+    /// :rtype: AudioDevice
+    #[pyo3(text_signature = "($self)")]
+    pub fn get_default_multimedia_output_device(&self) -> PyResult<PyAudioDevice> {
+        self._get_default_device(enums::DataFlow::Render, enums::Role::Multimedia)
+    }
+
+    /// Get the current default input device for the multimedia role (e.g. voice recording)
+    ///
+    /// :rtype: AudioDevice
+    #[pyo3(text_signature = "($self)")]
+    pub fn get_default_multimedia_input_device(&self) -> PyResult<PyAudioDevice> {
+        self._get_default_device(enums::DataFlow::Capture, enums::Role::Multimedia)
+    }
+
+    /// The master volume and mute state of every active output device
+    ///
+    /// :rtype: dict[str, tuple[float, bool]]
+    #[pyo3(text_signature = "($self)")]
+    pub fn output_volume_snapshot(&self) -> Result<HashMap<String, (f32, bool)>> {
+        let devices = self
+            .0
+            .get_collection(enums::DataFlow::Render, enums::DeviceState::Active)?;
+
+        let mut snapshot = HashMap::new();
+        for idx in 0..devices.length()? {
+            let device = devices.get(idx)?;
+            snapshot.insert(device.id.clone(), device.master_volume()?);
+        }
+        Ok(snapshot)
+    }
+
+    /// List the devices of `dataflow` as plain dicts, e.g. to show in a table or pass to
+    /// another process
+    ///
+    /// Every device of `dataflow` (by default either) is included whatever its state. Each dict
+    /// has exactly these keys, holding only strings and booleans so the list can be passed
+    /// straight to ``json.dumps``:
+    ///
+    /// - ``id``: the device id
+    /// - ``name``: the friendly name
+    /// - ``state``: ``"ACTIVE"``, ``"DISABLED"``, ``"NOT_PRESENT"`` or ``"UNPLUGGED"``
+    /// - ``data_flow``: ``"RENDER"`` or ``"CAPTURE"``
+    /// - ``default_console``, ``default_multimedia``, ``default_communications``: whether the
+    ///   device is the default of its data flow for that role
+    ///
+    /// :type dataflow: DataFlow | None
+    /// :rtype: list[dict[str, typing.Union[str, bool]]]
+    #[pyo3(text_signature = "($self, dataflow = None)")]
+    pub fn list_devices_json<'py>(
+        &self,
+        py: Python<'py>,
+        dataflow: Option<enums::DataFlow>,
+    ) -> Result<Vec<&'py PyDict>> {
+        // Look each default up once, rather than once per device
+        let mut defaults = Vec::new();
+        for flow in [enums::DataFlow::Render, enums::DataFlow::Capture] {
+            for role in enums::Role::ALL {
+                let id = self.0.get_default_device_id(flow.into(), role.into())?;
+                defaults.push((flow, role, id));
+            }
+        }
+        let is_default = |id: &str, flow: enums::DataFlow, role: enums::Role| {
+            defaults
+                .iter()
+                .any(|(f, r, default)| *f == flow && *r == role && default.as_deref() == Some(id))
+        };
+
+        let devices = self.0.get_collection(
+            dataflow.unwrap_or(enums::DataFlow::All),
+            enums::DeviceState::All,
+        )?;
+        let mut listing = Vec::new();
+        for idx in 0..devices.length()? {
+            let device = devices.get(idx)?;
+            let flow = device.data_flow()?;
+
+            let dict = PyDict::new(py);
+            dict.set_item("id", &device.id)?;
+            dict.set_item("name", &device.friendly_name)?;
+            dict.set_item("state", device.state()?.py_name())?;
+            dict.set_item("data_flow", flow.py_name())?;
+            dict.set_item(
+                "default_console",
+                is_default(&device.id, flow, enums::Role::Console),
+            )?;
+            dict.set_item(
+                "default_multimedia",
+                is_default(&device.id, flow, enums::Role::Multimedia),
+            )?;
+            dict.set_item(
+                "default_communications",
+                is_default(&device.id, flow, enums::Role::Communications),
+            )?;
+            listing.push(dict);
+        }
+        Ok(listing)
+    }
+
+    /// Mute (or unmute) every active device of the given data flow
+    ///
+    /// A device that can't be muted doesn't stop the others being muted. Returns a
+    /// ``(device_id, ok, error_message)`` tuple for each device.
+    ///
+    /// :type dataflow: DataFlow
+    /// :type muted: bool
+    /// :rtype: list[tuple[str, bool, typing.Optional[str]]]
+    #[pyo3(text_signature = "($self, dataflow, muted)")]
+    pub fn mute_all(&self, dataflow: enums::DataFlow, muted: bool) -> Result<BatchResult> {
+        self._mute_all(dataflow, muted)
+    }
+
+    /// Mute (or unmute) every active input device (microphone)
+    ///
+    /// A device that can't be muted doesn't stop the others being muted. Returns a
+    /// ``(device_id, ok, error_message)`` tuple for each device, as ``mute_all`` does.
+    ///
+    /// :type muted: bool
+    /// :rtype: list[tuple[str, bool, typing.Optional[str]]]
+    #[pyo3(text_signature = "($self, muted = True)")]
+    #[args(muted = "true")]
+    pub fn mute_all_inputs(&self, muted: bool) -> Result<BatchResult> {
+        self._mute_all(enums::DataFlow::Capture, muted)
+    }
+
+    /// Mute the devices with the given ids, then put back the mute state each had before after
+    /// `duration_ms` milliseconds
+    ///
+    /// This returns straight away: the mute states are put back by a background task, which doesn't
+    /// need this collection or anything else to keep running. A device that can't be found or muted
+    /// doesn't stop the others being muted. Returns a ``(device_id, ok, error_message)`` tuple for
+    /// each device.
+    ///
+    /// :type device_ids: list[str]
+    /// :type duration_ms: int
+    /// :rtype: list[tuple[str, bool, typing.Optional[str]]]
+    #[pyo3(text_signature = "($self, device_ids, duration_ms)")]
+    pub fn mute_schedule(&self, device_ids: Vec<String>, duration_ms: u64) -> BatchResult {
+        let mut outcome = BatchResult::default();
+        let mut restore = Vec::new();
+        for device_id in device_ids {
+            let muted = self.0.get_device(&device_id).and_then(|device| {
+                let (_, muted) = device.master_volume()?;
+                device.set_mute(true)?;
+                Ok((device, muted))
+            });
+            match muted {
+                Ok(previous) => {
+                    restore.push(previous);
+                    outcome.push(device_id, anyhow::Ok(()));
+                }
+                Err(err) => outcome.push(device_id, Err(err)),
+            }
+        }
+
+        task::spawn(async move {
+            task::sleep(Duration::from_millis(duration_ms)).await;
+            // This runs on an executor thread, which might not have been used for COM yet
+            com::com_initialized();
+            for (device, muted) in restore {
+                if let Err(err) = device.set_mute(muted) {
+                    warn!("Unable to restore mute state of {}: {}", device.id, err);
+                }
+            }
+        });
+        outcome
+    }
+
+    /// Toggle the mute of every active endpoint of a physical device, such as a headset's
+    /// speakers and microphone together
+    ///
+    /// The endpoints are those ``devices_in_container`` finds for `container_id`. Which way to
+    /// toggle is decided by the output endpoint (or the first endpoint, if there is no output):
+    /// if it is muted everything is unmuted, otherwise everything is muted. Raises KeyError if no
+    /// active endpoint is in the container. Returns the new mute state.
+    ///
+    /// An endpoint that can't be toggled doesn't stop the others. If any failed, the first error
+    /// is raised once the rest have been toggled, and the container is left partly toggled.
+    ///
+    /// :type container_id: str
+    /// :rtype: bool
+    #[pyo3(text_signature = "($self, container_id)")]
+    pub fn toggle_container_mute(&self, container_id: &str) -> Result<bool> {
+        let devices = self.devices_in_container(container_id, None)?;
+
+        let mut representative = None;
+        for device in &devices {
+            if device.0.data_flow()? == enums::DataFlow::Render {
+                representative = Some(device);
+                break;
+            }
+        }
+        let representative = match representative.or_else(|| devices.first()) {
+            Some(device) => device,
+            None => {
+                return Err(PyKeyError::new_err(format!(
+                    "no active device in container {}",
+                    container_id
+                ))
+                .into())
+            }
+        };
+
+        let (_, muted) = representative.0.master_volume()?;
+        let mut failure = None;
+        for device in &devices {
+            if let Err(err) = device.0.set_mute(!muted) {
+                warn!("Unable to set mute of device {}: {:#}", device.0.id, err);
+                failure.get_or_insert(err);
+            }
+        }
+        match failure {
+            Some(err) => Err(err.into()),
+            None => Ok(!muted),
+        }
+    }
+
+    /// Toggle the mute of whichever device is currently the default output
+    ///
+    /// The default device is looked up on every call, so this follows changes of default.
+    /// Returns the new mute state.
+    ///
+    /// :rtype: bool
+    #[pyo3(text_signature = "($self)")]
+    pub fn toggle_default_output_mute(&self) -> Result<bool> {
+        let device = self._get_default_device(enums::DataFlow::Render, enums::Role::Console)?;
+        Ok(device.0.toggle_mute()?)
+    }
+
+    /// The channel count and physical speaker positions of the current default output device
+    ///
+    /// Returns ``(channel_count, physical_speakers)``, as the ``AudioDevice`` properties of
+    /// those names, or None if there is no output device.
+    ///
+    /// :rtype: tuple[int, list[str]] | None
+    #[pyo3(text_signature = "($self)")]
+    pub fn default_output_channels(&self, py: Python) -> Result<Option<(u32, Vec<&'static str>)>> {
+        let device = match self._get_default_device(enums::DataFlow::Render, enums::Role::Console) {
+            Ok(device) => device,
+            Err(err) if err.is_instance_of::<PyKeyError>(py) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Some((
+            device.0.channel_count()?,
+            device::speaker_positions(device.0.physical_speakers()?),
+        )))
+    }
+
+    /// Raise the volume of whichever device is currently the default output by one step
+    ///
+    /// This uses the device's own step size, the same as the keyboard volume keys, and follows
+    /// changes of default.
+    #[pyo3(text_signature = "($self)")]
+    pub fn default_output_step_up(&self) -> Result<()> {
+        let device = self._get_default_device(enums::DataFlow::Render, enums::Role::Console)?;
+        Ok(device.0.volume_step(true)?)
+    }
+
+    /// Lower the volume of whichever device is currently the default output by one step
+    ///
+    /// This uses the device's own step size, the same as the keyboard volume keys, and follows
+    /// changes of default.
+    #[pyo3(text_signature = "($self)")]
+    pub fn default_output_step_down(&self) -> Result<()> {
+        let device = self._get_default_device(enums::DataFlow::Render, enums::Role::Console)?;
+        Ok(device.0.volume_step(false)?)
+    }
+
+    /// Toggle the mute of whichever device is currently the default input (aka microphone)
+    ///
+    /// The default device is looked up on every call, so this follows changes of default.
+    /// Returns the new mute state.
+    ///
+    /// :rtype: bool
+    #[pyo3(text_signature = "($self)")]
+    pub fn toggle_default_input_mute(&self) -> Result<bool> {
+        let device = self._get_default_device(enums::DataFlow::Capture, enums::Role::Console)?;
+        Ok(device.0.toggle_mute()?)
+    }
+
+    /// :rtype: CollectionEventsIterator
+    ///
+    ///Asyncronoysly yield the events for this collection (device added or reomved, default changed, etc)
+    #[getter]
+    pub fn events(slf: Py<Self>, py: Python<'_>) -> Result<CollectionEventsIterator> {
+        Self::collection_events(slf.as_ref(py), false, false, false, false)
+    }
+
+    /// Asyncronoysly yield the events for this collection, with options
+    ///
+    /// With `resolve_defaults`, DEFAULT_CHANGED events carry the new default ``AudioDevice`` in
+    /// their ``device`` attribute, saving a lookup by id.
+    ///
+    /// With `dedupe`, an event identical to the one immediately before it is dropped, as Windows
+    /// sometimes notifies the same change more than once.
+    ///
+    /// Windows calls the library from its own threads, and by default the events are handed on
+    /// to this iterator by tasks on the async-std executor, whose small pool of threads is shared
+    /// with the library's other background work. With `background_thread`, a dedicated OS thread
+    /// does that instead, so events aren't held up while that pool is busy (e.g. with callbacks
+    /// waiting for the GIL).
+    ///
+    /// With `include_metadata`, events carry the device's friendly name in their ``name``
+    /// attribute, and ADDED events its ``state``. Names are remembered, so REMOVED events are
+    /// labelled too even though the device can no longer be looked up.
+    ///
+    /// :type resolve_defaults: bool
+    /// :type dedupe: bool
+    /// :type background_thread: bool
+    /// :type include_metadata: bool
+    /// :rtype: CollectionEventsIterator
+    #[pyo3(
+        text_signature = "($self, resolve_defaults = False, dedupe = False, background_thread = False, include_metadata = False)"
+    )]
+    #[args(
+        resolve_defaults = "false",
+        dedupe = "false",
+        background_thread = "false",
+        include_metadata = "false"
+    )]
+    pub fn collection_events(
+        slf: &PyCell<Self>,
+        resolve_defaults: bool,
+        dedupe: bool,
+        background_thread: bool,
+        include_metadata: bool,
+    ) -> Result<CollectionEventsIterator> {
+        let (tx, rx) = bounded(1);
+
+        let (tx, dedupe_stage) = if dedupe {
+            let (raw_tx, raw_rx) = bounded(1);
+            let stage = drain::dedupe(raw_rx, tx, |event: &anyhow::Result<_>| {
+                event.as_ref().ok().cloned()
+            });
+            (raw_tx, Some(stage))
+        } else {
+            (tx, None)
+        };
+
+        let sender = if background_thread {
+            if let Some(stage) = dedupe_stage {
+                std::thread::spawn(move || task::block_on(stage));
+            }
+            drain::DrainSender::on_thread(tx)?
+        } else {
+            if let Some(stage) = dedupe_stage {
+                task::spawn(stage);
+            }
+            drain::DrainSender::new(tx)
+        };
+        let source = NotificationClient::with_sender(sender)?;
+
+        let enumerator = slf.borrow().0.clone();
+        let names = match include_metadata {
+            true => Some(DeviceNames::new(enumerator.as_ref())?),
+            false => None,
+        };
+        enumerator.register_notification(&source)?;
+
+        Ok(CollectionEventsIterator {
+            enumerator,
+            source: Some(source),
+            rx,
+            resolve_defaults,
+            names,
+        })
+    }
+
+    /// :rtype: DefaultOutputEventsIterator
+    ///
+    /// Asynchronously yield the volume events of the default output device, following it when the default changes
+    ///
+    /// Whenever a different device becomes the default, an event with that device's current volume is yielded first.
+    #[getter]
+    pub fn default_output_events(slf: &PyCell<Self>) -> Result<DefaultOutputEventsIterator> {
+        let (events_tx, events_rx) = bounded(1);
+        let (tx, rx) = bounded(1);
+
+        let source = NotificationClient::new(events_tx)?;
+
+        let enumerator = slf.borrow().0.clone();
+        enumerator.register_notification(&source)?;
+
+        task::spawn(follow_default_output(enumerator, events_rx, tx));
+
+        Ok(DefaultOutputEventsIterator {
+            collection: slf.into(),
+            source: Some(source),
+            rx,
+        })
+    }
+
+    /// Asynchronously yield the volume events of whichever device is the default output
+    ///
+    /// The same as the ``default_output_events`` property, for use as
+    /// ``async for event in collection.default_output_volume_events():``.
+    ///
+    /// :rtype: DefaultOutputEventsIterator
+    #[pyo3(text_signature = "($self)")]
+    pub fn default_output_volume_events(slf: &PyCell<Self>) -> Result<DefaultOutputEventsIterator> {
+        Self::default_output_events(slf)
+    }
+
+    /// Asynchronously yield the new default ``AudioDevice`` each time the default changes
+    ///
+    /// Only changes for `dataflow` and `role` are yielded, where given; otherwise changes for
+    /// every data flow and role are. Added, removed and state change events are skipped.
+    ///
+    /// :type dataflow: DataFlow | None
+    /// :type role: Role | None
+    /// :rtype: DefaultChangeEventsIterator
+    #[pyo3(text_signature = "($self, dataflow = None, role = None)")]
+    pub fn default_change_events(
+        &self,
+        dataflow: Option<enums::DataFlow>,
+        role: Option<enums::Role>,
+    ) -> Result<DefaultChangeEventsIterator> {
+        let (tx, rx) = bounded(1);
+
+        let source = NotificationClient::new(tx)?;
+
+        let enumerator = self.0.clone();
+        enumerator.register_notification(&source)?;
+
+        Ok(DefaultChangeEventsIterator {
+            enumerator,
+            source: Some(source),
+            rx,
+            dataflow,
+            role,
+        })
+    }
+
+    /// Call `callback` with each event for this collection, without needing an asyncio loop
+    ///
+    /// The callback is invoked from a background thread with a single DeviceCollectionEvent
+    /// argument. It stays registered until the returned object is closed or garbage collected.
+    ///
+    /// :type callback: typing.Callable
+    /// :rtype: CollectionCallback
+    #[pyo3(text_signature = "($self, callback)")]
+    pub fn register_collection_callback(
+        slf: &PyCell<Self>,
+        callback: PyObject,
+    ) -> Result<CollectionCallback> {
+        Self::watch(slf, callback, None)
+    }
+
+    /// Watch this collection in the background, calling `on_event` with each
+    /// DeviceCollectionEvent
+    ///
+    /// This is the same as ``register_collection_callback``, with somewhere to send errors: an
+    /// exception raised by `on_event`, or an error reading an event, is passed to `on_error` and
+    /// the watcher carries on with the next event. Without `on_error`, exceptions are printed and
+    /// errors reading an event are logged as warnings. Call ``stop()`` on the returned object to
+    /// stop watching.
+    ///
+    /// :type on_event: typing.Callable[[DeviceCollectionEvent], typing.Any]
+    /// :type on_error: typing.Callable[[Exception], typing.Any] | None
+    /// :rtype: CollectionCallback
+    #[pyo3(text_signature = "($self, on_event, on_error = None)")]
+    pub fn watch(
+        slf: &PyCell<Self>,
+        on_event: PyObject,
+        on_error: Option<PyObject>,
+    ) -> Result<CollectionCallback> {
+        let (tx, rx) = bounded(1);
+
+        let source = NotificationClient::new(tx)?;
+
+        slf.borrow().0.register_notification(&source)?;
+
+        task::spawn(async move {
+            while let Ok(msg) = rx.recv().await {
+                Python::with_gil(|py| {
+                    let err = match msg {
+                        Ok(event) => {
+                            let pyevent: PyDeviceCollectionEvent = event.into();
+                            match on_event.call1(py, (pyevent,)) {
+                                Ok(_) => return,
+                                Err(err) => err,
+                            }
+                        }
+                        Err(err) if on_error.is_none() => {
+                            warn!("{:#}", err);
+                            return;
+                        }
+                        Err(err) => into_pyerr(err),
+                    };
+                    // Nothing the handlers raise may stop the watcher
+                    match &on_error {
+                        Some(on_error) => {
+                            if let Err(err) = on_error.call1(py, (err.value(py),)) {
+                                err.print(py);
+                            }
+                        }
+                        None => err.print(py),
+                    }
+                });
+            }
+        });
+
+        Ok(CollectionCallback {
+            collection: slf.into(),
+            source: Some(source),
+        })
+    }
+
+    /// Stop every event iterator and callback created through this collection
+    ///
+    /// This covers the collection's own event iterators and callbacks, and the volume, property and
+    /// state events of devices obtained from it, which all end as though they had been closed. It is
+    /// called when leaving a ``with collection:`` block.
+    #[pyo3(text_signature = "($self)")]
+    pub fn close_all(&self) -> Result<()> {
+        Ok(self.0.close_all()?)
+    }
+
+    /// :rtype: DeviceCollection
+    #[pyo3(text_signature = "($self)")]
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// :rtype: bool
+    #[pyo3(text_signature = "($self, exc_type, exc_value, traceback)")]
+    fn __exit__(&self, _exc_type: &PyAny, _exc_value: &PyAny, _traceback: &PyAny) -> Result<bool> {
+        self.close_all()?;
+        Ok(false)
+    }
+}
+
+/// Outcome of an operation on several targets, where one failing doesn't stop the others
+#[derive(Default)]
+struct BatchResult(Vec<(String, anyhow::Result<()>)>);
+
+impl BatchResult {
+    fn push<E: Into<anyhow::Error>>(&mut self, target: String, result: std::result::Result<(), E>) {
+        self.0.push((target, result.map_err(Into::into)));
+    }
+
+    /// The outcomes as Python sees them: ``(target, ok, error_message)``
+    fn outcomes(self) -> Vec<(String, bool, Option<String>)> {
+        self.0
+            .into_iter()
+            .map(|(target, result)| match result {
+                Ok(()) => (target, true, None),
+                Err(err) => (target, false, Some(format!("{:#}", err))),
+            })
+            .collect()
+    }
+}
+
+impl IntoPy<PyObject> for BatchResult {
+    fn into_py(self, py: Python) -> PyObject {
+        self.outcomes().into_py(py)
+    }
+}
+
+#[pyclass(module = "windows_audio_control")]
+/// Snapshot of the default output and input devices and their volume
+struct DefaultSummary {
+    /// The default output device and its volume, or None if there is no output device
+    ///
+    /// :rtype: VolumeChangeEvent | None
+    #[pyo3(get)]
+    output: Option<Py<PyVolumeChangeEvent>>,
+
+    /// The default input device and its volume, or None if there is no input device
+    ///
+    /// :rtype: VolumeChangeEvent | None
+    #[pyo3(get)]
+    input: Option<Py<PyVolumeChangeEvent>>,
+}
+
+#[pyclass(module = "windows_audio_control")]
+/// Snapshot of the whole system's audio state, for a status display or health check
+struct SystemAudioState {
+    /// The default output device and its volume, or None if there is no output device
+    ///
+    /// :rtype: VolumeChangeEvent | None
+    #[pyo3(get)]
+    output: Option<Py<PyVolumeChangeEvent>>,
+
+    /// The peak level of the default output device, or None if there is none or it has no meter
+    ///
+    /// :rtype: float | None
+    #[pyo3(get)]
+    output_peak: Option<f32>,
+
+    /// The default input device and its volume, or None if there is no input device
+    ///
+    /// :rtype: VolumeChangeEvent | None
+    #[pyo3(get)]
+    input: Option<Py<PyVolumeChangeEvent>>,
+
+    /// Number of active output devices
+    ///
+    /// :rtype: int
+    #[pyo3(get)]
+    active_outputs: usize,
+
+    /// Number of active input devices
+    ///
+    /// :rtype: int
+    #[pyo3(get)]
+    active_inputs: usize,
+}
+
+#[pymethods]
+impl SystemAudioState {
+    /// The snapshot as a plain dict, with the devices' states as ``VolumeChangeEvent.to_dict``
+    ///
+    /// :rtype: dict[str, typing.Any]
+    #[pyo3(text_signature = "($self)")]
+    fn to_dict<'a>(&self, py: Python<'a>) -> PyResult<&'a PyDict> {
+        let event_dict = |event: &Option<Py<PyVolumeChangeEvent>>| {
+            event
+                .as_ref()
+                .map(|event| event.borrow(py).to_dict(py))
+                .transpose()
+        };
+
+        let dict = PyDict::new(py);
+        dict.set_item("output", event_dict(&self.output)?)?;
+        dict.set_item("output_peak", self.output_peak)?;
+        dict.set_item("input", event_dict(&self.input)?)?;
+        dict.set_item("active_outputs", self.active_outputs)?;
+        dict.set_item("active_inputs", self.active_inputs)?;
+        Ok(dict)
+    }
+}
+
+#[pyclass(module = "windows_audio_control", unsendable)]
+/// Registration of a callback for changes to devices in a collection
+struct CollectionCallback {
+    // Keep the collection alive as long as the callback is registered
+    collection: Py<PyDeviceCollection>,
+    source: Option<IMMNotificationClient>,
+}
+
+#[pymethods]
+impl CollectionCallback {
+    /// Stop calling the callback, the same as ``close()``
+    #[pyo3(text_signature = "($self)")]
+    pub fn stop(&mut self, py: Python) -> Result<()> {
+        self.close(py)
+    }
+
+    /// Stop calling the callback
+    #[pyo3(text_signature = "($self)")]
+    pub fn close(&mut self, py: Python) -> Result<()> {
+        if let Some(source) = self.source.as_ref() {
+            let obj = self.collection.borrow(py);
+            let collection = obj.0.as_ref();
+            collection
+                .unregister_notification(source)
+                .context("Unable to close CollectionCallback")?;
+            self.source = None
+        }
+        Ok(())
+    }
+}
+
+impl Drop for CollectionCallback {
+    fn drop(&mut self) {
+        _ = Python::with_gil(|py| self.close(py));
+    }
+}
+
+/// Friendly names of the devices an iterator has seen, so that events for devices which have
+/// since been removed can still be labelled
+#[derive(Clone)]
+struct DeviceNames(Arc<Mutex<HashMap<String, String>>>);
+
+impl DeviceNames {
+    /// Names of all the devices there are now
+    fn new(enumerator: &dyn DeviceSource) -> Result<Self> {
+        let devices = enumerator.get_collection(enums::DataFlow::All, enums::DeviceState::All)?;
+        let mut names = HashMap::new();
+        for idx in 0..devices.length()? {
+            match devices.get(idx) {
+                Ok(device) => {
+                    names.insert(device.id.clone(), device.friendly_name.clone());
+                }
+                // One device that can't be read (e.g. because it is being removed) is left
+                // unnamed, rather than stopping the others being named
+                Err(err) => debug!("Unable to read device #{}: {:#}", idx, err),
+            }
+        }
+        Ok(DeviceNames(Arc::new(Mutex::new(names))))
+    }
+
+    /// Set the `name` (and for ADDED events, `state`) of `event`
+    ///
+    /// A device that has already gone again is named from what was known of it before.
+    fn annotate(
+        &self,
+        enumerator: &dyn DeviceSource,
+        event: &mut PyDeviceCollectionEvent,
+    ) -> anyhow::Result<()> {
+        if matches!(
+            event.kind,
+            DeviceCollectionEventType::Added | DeviceCollectionEventType::StateChanged
+        ) {
+            // The lock is only taken once the COM calls are done
+            match enumerator.get_device(&event.device_id) {
+                Ok(device) => {
+                    if event.kind == DeviceCollectionEventType::Added {
+                        event.state = match device.state() {
+                            Ok(state) => Some(state),
+                            Err(err) if device_gone(&err) => None,
+                            Err(err) => return Err(err),
+                        };
+                    }
+                    let mut names = self.0.lock().unwrap();
+                    names.insert(device.id.clone(), device.friendly_name.clone());
+                }
+                Err(err) if device_gone(&err) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        event.name = self.0.lock().unwrap().get(&event.device_id).cloned();
+        Ok(())
+    }
+}
+
+#[pyclass(module = "windows_audio_control", subclass, unsendable)]
+/// Async iterator of changes to devices in a collection
+struct CollectionEventsIterator {
+    // Held directly rather than through the DeviceCollection, so closing doesn't need the GIL
+    enumerator: Arc<dyn DeviceSource>,
+    source: Option<IMMNotificationClient>,
+    rx: Receiver<anyhow::Result<collection::DeviceNotificationEvent>>,
+    resolve_defaults: bool,
+    names: Option<DeviceNames>,
+}
+
+impl CollectionEventsIterator {
+    fn _next_event<'a>(&'a mut self, py: Python<'a>) -> PyResult<&'a PyAny> {
+        let rx = self.rx.clone();
+        let enumerator = self.enumerator.clone();
+        let resolve_defaults = self.resolve_defaults;
+        let names = self.names.clone();
+        pyo3_asyncio::async_std::future_into_py(py, async move {
+            match rx.recv().await {
+                Ok(val) => {
+                    // This runs on an executor thread (which may differ from one await to the
+                    // next), and that might not have been used for COM yet
+                    com::com_initialized();
+                    let pyevent = Self::convert(
+                        enumerator.as_ref(),
+                        val.map_err(into_pyerr)?,
+                        resolve_defaults,
+                        names.as_ref(),
+                    )?;
+
+                    Ok(Python::with_gil(|py| pyevent.into_py(py)))
+                }
+                Err(RecvError) => Err(PyStopAsyncIteration::new_err("device enumerator closed")),
+            }
+        })
+    }
+
+    /// Convert `event` for Python, looking up the new default device if `resolve_defaults` is set
+    /// and adding the device's metadata if there are `names`
+    fn convert(
+        enumerator: &dyn DeviceSource,
+        event: collection::DeviceNotificationEvent,
+        resolve_defaults: bool,
+        names: Option<&DeviceNames>,
+    ) -> PyResult<PyDeviceCollectionEvent> {
+        let mut pyevent: PyDeviceCollectionEvent = event.into();
+        if let Some(names) = names {
+            names
+                .annotate(enumerator, &mut pyevent)
+                .map_err(into_pyerr)?;
+        }
+        if resolve_defaults && pyevent.kind == DeviceCollectionEventType::DefaultChanged {
+            match enumerator.get_device(&pyevent.device_id) {
+                Ok(dev) => {
+                    pyevent.device = Some(Python::with_gil(|py| Py::new(py, PyAudioDevice(dev)))?);
+                }
+                // The device may already have gone again, in which case there's nothing to attach
+                Err(err) if device_gone(&err) => {}
+                Err(err) => return Err(into_pyerr(err)),
+            }
+        }
+        Ok(pyevent)
+    }
+}
+
+#[pymethods]
+impl CollectionEventsIterator {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// :rtype: DeviceCollectionEvent
+    ///
+    pub fn __anext__<'a>(&'a mut self, py: Python<'a>) -> PyResult<Option<&'a PyAny>> {
+        if self.source.is_none() {
+            return Err(PyStopAsyncIteration::new_err("iterator closed"));
+        }
+        match self._next_event(py) {
+            Ok(event) => Ok(Some(event)),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Whether ``close()`` has been called on this iterator
+    ///
+    /// :rtype: bool
+    #[getter]
+    pub fn closed(&self) -> bool {
+        self.source.is_none()
+    }
+
+    /// Return the next event if one is already waiting, otherwise None, without waiting
+    ///
+    /// :rtype: DeviceCollectionEvent | None
+    #[pyo3(text_signature = "($self)")]
+    pub fn try_next(&self) -> Result<Option<PyDeviceCollectionEvent>> {
+        match self.rx.try_recv() {
+            Ok(val) => Ok(Some(Self::convert(
+                self.enumerator.as_ref(),
+                val?,
+                self.resolve_defaults,
+                self.names.as_ref(),
+            )?)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Return all the events that are already waiting, without waiting for more
+    ///
+    /// :rtype: list[DeviceCollectionEvent]
+    #[pyo3(text_signature = "($self)")]
+    pub fn drain(&self) -> PyResult<Vec<PyDeviceCollectionEvent>> {
+        let mut events = Vec::new();
+        while let Some(event) = self.try_next()? {
+            events.push(event);
+        }
+        Ok(events)
+    }
+
+    /// Close the iterator
+    #[pyo3(text_signature = "($self)")]
+    pub fn close(&mut self) -> Result<()> {
+        if let Some(source) = self.source.as_ref() {
+            self.enumerator
+                .unregister_notification(source)
+                .context("Unable to close CollectionEventsIterator")?;
+            self.source = None
+        }
+        Ok(())
+    }
+}
+
+impl Drop for CollectionEventsIterator {
+    // This doesn't take the GIL, which may not be available when the interpreter is shutting down
+    fn drop(&mut self) {
+        _ = self.close();
+    }
+}
+
+/// Forward the volume events of whichever device is the default output to `tx`, until `events` closes
+async fn follow_default_output(
+    enumerator: Arc<dyn DeviceSource>,
+    events: Receiver<anyhow::Result<collection::DeviceNotificationEvent>>,
+    tx: Sender<PyVolumeChangeEvent>,
+) {
+    // This runs on an executor thread, which might not have been used for COM yet
+    com::com_initialized();
+
+    let mut current = follow_device(
+        enumerator.get_default_device(enums::DataFlow::Render.into(), enums::Role::Console.into()),
+        &tx,
+    )
+    .await;
+
+    while let Ok(msg) = events.recv().await {
+        let device_id = match msg {
+            Ok(collection::DeviceNotificationEvent::DefaultChanged(
+                device_id,
+                enums::DataFlow::Render,
+                enums::Role::Console,
+            )) => device_id,
+            _ => continue,
+        };
+
+        if let Some(device) = current.take() {
+            Python::with_gil(|py| device.borrow_mut(py).0.stop_listening());
+        }
+        current = follow_device(enumerator.get_device(&device_id), &tx).await;
+    }
+
+    if let Some(device) = current {
+        Python::with_gil(|py| device.borrow_mut(py).0.stop_listening());
+    }
+}
+
+/// Start forwarding the volume events of `device` to `tx`, after sending its current state
+async fn follow_device(
+    device: anyhow::Result<device::AudioDevice>,
+    tx: &Sender<PyVolumeChangeEvent>,
+) -> Option<Py<PyAudioDevice>> {
+    let result = async {
+        let mut device = device?;
+        let initial = device.volume_state()?;
+
+        let (device_tx, device_rx) = bounded(1);
+        device.register_volume_change(
+            drain::DrainSender::new(device_tx),
+            false,
+            true,
+            false,
+            Duration::ZERO,
+        )?;
+
+        let device = Python::with_gil(|py| Py::new(py, PyAudioDevice(device)))?;
+
+        tx.send(PyVolumeChangeEvent::new(device.clone(), initial))
+            .await?;
+
+        let forward_device = device.clone();
+        let tx = tx.clone();
+        task::spawn(async move {
+            while let Ok(event) = device_rx.recv().await {
+                let pyevent = PyVolumeChangeEvent::new(forward_device.clone(), event);
+                if tx.send(pyevent).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        anyhow::Ok(device)
+    };
+
+    match result.await {
+        Ok(device) => Some(device),
+        Err(err) => {
+            debug!("Unable to follow default output device: {:#}", err);
+            None
+        }
+    }
+}
+
+#[pyclass(module = "windows_audio_control", subclass, unsendable)]
+/// Async iterator of the volume changes of the default output device, whichever device that is
+struct DefaultOutputEventsIterator {
+    // Keep the collection alive as long as the iterator is
+    collection: Py<PyDeviceCollection>,
+    source: Option<IMMNotificationClient>,
+    rx: Receiver<PyVolumeChangeEvent>,
+}
+
+#[pymethods]
+impl DefaultOutputEventsIterator {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// :rtype: VolumeChangeEvent
+    ///
+    pub fn __anext__<'a>(&'a mut self, py: Python<'a>) -> PyResult<Option<&'a PyAny>> {
+        let rx = self.rx.clone();
+        let fut = pyo3_asyncio::async_std::future_into_py(py, async move {
+            match rx.recv().await {
+                Ok(pyevent) => Ok(Python::with_gil(|py| pyevent.into_py(py))),
+                Err(RecvError) => Err(PyStopAsyncIteration::new_err("device enumerator closed")),
+            }
+        })?;
+        Ok(Some(fut))
+    }
+
+    /// Close the iterator
+    #[pyo3(text_signature = "($self)")]
+    pub fn close(&mut self, py: Python) -> Result<()> {
+        if let Some(source) = self.source.as_ref() {
+            let obj = self.collection.borrow(py);
+            let collection = obj.0.as_ref();
+            collection
+                .unregister_notification(source)
+                .context("Unable to close DefaultOutputEventsIterator")?;
+            self.source = None
+        }
+        Ok(())
+    }
+}
+
+impl Drop for DefaultOutputEventsIterator {
+    fn drop(&mut self) {
+        _ = Python::with_gil(|py| self.close(py));
+    }
+}
+
+#[pyclass(module = "windows_audio_control", subclass, unsendable)]
+/// Async iterator of the devices that become the default
+struct DefaultChangeEventsIterator {
+    enumerator: Arc<dyn DeviceSource>,
+    source: Option<IMMNotificationClient>,
+    rx: Receiver<anyhow::Result<collection::DeviceNotificationEvent>>,
+    dataflow: Option<enums::DataFlow>,
+    role: Option<enums::Role>,
+}
+
+#[pymethods]
+impl DefaultChangeEventsIterator {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// :rtype: AudioDevice
+    ///
+    pub fn __anext__<'a>(&'a mut self, py: Python<'a>) -> PyResult<Option<&'a PyAny>> {
+        if self.source.is_none() {
+            return Err(PyStopAsyncIteration::new_err("iterator closed"));
+        }
+        let rx = self.rx.clone();
+        let enumerator = self.enumerator.clone();
+        let (dataflow, role) = (self.dataflow, self.role);
+        let fut = pyo3_asyncio::async_std::future_into_py(py, async move {
+            loop {
+                let event = match rx.recv().await {
+                    Ok(val) => val.map_err(into_pyerr)?,
+                    Err(RecvError) => {
+                        return Err(PyStopAsyncIteration::new_err("device enumerator closed"))
+                    }
+                };
+                let device_id = match event.default_changed_to(dataflow, role) {
+                    Some(device_id) => device_id,
+                    None => continue,
+                };
+                // This runs on an executor thread (which may differ from one await to the next),
+                // and that might not have been used for COM yet
+                com::com_initialized();
+                match enumerator.get_device(device_id) {
+                    Ok(dev) => return Ok(Python::with_gil(|py| PyAudioDevice(dev).into_py(py))),
+                    // The device may already have gone again, in which case a later event will
+                    // follow
+                    Err(err) if device_gone(&err) => {
+                        debug!("New default device {} has already gone", device_id)
+                    }
+                    Err(err) => return Err(into_pyerr(err)),
+                }
+            }
+        })?;
+        Ok(Some(fut))
+    }
+
+    /// Close the iterator
+    #[pyo3(text_signature = "($self)")]
+    pub fn close(&mut self) -> Result<()> {
+        if let Some(source) = self.source.as_ref() {
+            self.enumerator
+                .unregister_notification(source)
+                .context("Unable to close DefaultChangeEventsIterator")?;
+            self.source = None
+        }
+        Ok(())
+    }
+}
+
+impl Drop for DefaultChangeEventsIterator {
+    fn drop(&mut self) {
+        _ = self.close();
+    }
+}
+
+#[pyclass(module = "windows_audio_control", subclass, unsendable)]
+/// Async iterator of one kind of collection event, for a single device
+struct DeviceNotificationsIterator {
+    enumerator: Arc<dyn DeviceSource>,
+    source: Option<IMMNotificationClient>,
+    // Normalized, as the ids of the events are before they are compared with it
+    device_id: String,
+    kind: DeviceCollectionEventType,
+    rx: Receiver<anyhow::Result<collection::DeviceNotificationEvent>>,
+}
+
+impl DeviceNotificationsIterator {
+    /// Listen through `enumerator`, that of the collection the device came from, so that closing
+    /// the collection's listeners ends this iterator too
+    fn new(
+        enumerator: Arc<dyn DeviceSource>,
+        device_id: &str,
+        kind: DeviceCollectionEventType,
+    ) -> Result<Self> {
+        // Notifications are only available for a whole collection, so listen to them all and
+        // filter for this device
+        let (tx, rx) = bounded(1);
+
+        let source = NotificationClient::new(tx)?;
+        enumerator.register_notification(&source)?;
+
+        Ok(DeviceNotificationsIterator {
+            enumerator,
+            source: Some(source),
+            device_id: device::normalize_device_id(device_id),
+            kind,
+            rx,
+        })
+    }
+
+    /// The next event of `kind` about `device_id` sent on `rx`, or None once the channel is closed
+    async fn next_event(
+        rx: Receiver<anyhow::Result<collection::DeviceNotificationEvent>>,
+        device_id: String,
+        kind: DeviceCollectionEventType,
+    ) -> Option<anyhow::Result<PyDeviceCollectionEvent>> {
+        while let Ok(val) = rx.recv().await {
+            let event = match val {
+                Ok(event) => event,
+                Err(err) => return Some(Err(err)),
+            };
+            if device::normalize_device_id(event.device_id()) != device_id {
+                continue;
+            }
+            let pyevent: PyDeviceCollectionEvent = event.into();
+            if pyevent.kind == kind {
+                return Some(Ok(pyevent));
+            }
+        }
+        None
+    }
+}
+
+#[pymethods]
+impl DeviceNotificationsIterator {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// :rtype: DeviceCollectionEvent
+    ///
+    pub fn __anext__<'a>(&'a mut self, py: Python<'a>) -> PyResult<Option<&'a PyAny>> {
+        if self.source.is_none() {
+            return Err(PyStopAsyncIteration::new_err("iterator closed"));
+        }
+        let next = Self::next_event(self.rx.clone(), self.device_id.clone(), self.kind.clone());
+        let fut = pyo3_asyncio::async_std::future_into_py(py, async move {
+            match next.await {
+                Some(event) => {
+                    let pyevent = event.map_err(into_pyerr)?;
+                    Ok(Python::with_gil(|py| pyevent.into_py(py)))
+                }
+                None => Err(PyStopAsyncIteration::new_err("device enumerator closed")),
+            }
+        })?;
+        Ok(Some(fut))
+    }
+
+    /// Close the iterator
+    #[pyo3(text_signature = "($self)")]
+    pub fn close(&mut self) -> Result<()> {
+        if let Some(source) = self.source.as_ref() {
+            self.enumerator
+                .unregister_notification(source)
+                .context("Unable to close DeviceNotificationsIterator")?;
+            self.source = None
+        }
+        Ok(())
+    }
+}
+
+impl Drop for DeviceNotificationsIterator {
+    fn drop(&mut self) {
+        _ = self.close();
+    }
+}
+
+#[pyclass(module = "windows_audio_control", subclass, unsendable)]
+/// Async iterator of changes to a device's volume
+struct AudioDeviceEventIterator {
+    // Keep the device alive so we can use it in `repr`, but don't create a _rust_ memory cycle
+    #[pyo3(get)]
+    device: Py<PyAudioDevice>,
+    rx: Receiver<device::VolumeChangeEvent>,
+    // Held separately from the device, so stopping doesn't need the GIL
+    listener: Option<device::VolumeListener>,
+    // With unit="db", each event's volume is replaced by its volume_db
+    in_db: bool,
+    stopped: bool,
+}
+
+impl AudioDeviceEventIterator {
+    fn event(
+        device: Py<PyAudioDevice>,
+        in_db: bool,
+        mut val: device::VolumeChangeEvent,
+    ) -> PyResult<PyVolumeChangeEvent> {
+        if in_db {
+            // Windows doesn't expose how it maps the scalar to dB, so there's nothing to fall back on
+            val.volume = val.volume_db.ok_or_else(|| {
+                PyOSError::new_err("Unable to read the volume in dB from the device")
+            })?;
+        }
+        Ok(PyVolumeChangeEvent::new(device, val))
+    }
+
+    pub fn _next_event<'a>(&'a mut self, py: Python<'a>) -> PyResult<&'a PyAny> {
+        let rx = self.rx.clone();
+        let device = self.device.clone();
+        let in_db = self.in_db;
+        pyo3_asyncio::async_std::future_into_py(py, async move {
+            match rx.recv().await {
+                Ok(val) => {
+                    let pyevent = Self::event(device, in_db, val)?;
+                    Ok(Python::with_gil(|py| pyevent.into_py(py)))
+                }
+                Err(RecvError) => Err(PyStopAsyncIteration::new_err("audio session closed")),
+            }
+        })
+    }
+}
+
+#[pymethods]
+impl AudioDeviceEventIterator {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
     }
 
     pub fn __anext__<'a>(&'a mut self, py: Python<'a>) -> PyResult<Option<&'a PyAny>> {
+        if self.stopped {
+            return Err(PyStopAsyncIteration::new_err("iterator stopped"));
+        }
         match self._next_event(py) {
             Ok(event) => Ok(Some(event)),
             Err(err) => Err(err),
         }
     }
-}
 
-impl Drop for AudioDeviceEventIterator {
-    // When the iterator goes out of scope, stop listening for changes
-    fn drop(&mut self) {
-        Python::with_gil(|py| {
-            let mut d = self.device.borrow_mut(py);
-            d.0.stop_listening()
-        })
+    /// Stop listening for changes to the device, ending the iteration
+    #[pyo3(text_signature = "($self)")]
+    pub fn stop(&mut self) {
+        if !self.stopped {
+            if let Some(listener) = self.listener.take() {
+                listener.stop();
+            }
+            self.rx.close();
+            self.stopped = true;
+        }
+    }
+
+    /// Return the next event if one is already waiting, otherwise None, without waiting
+    ///
+    /// :rtype: VolumeChangeEvent | None
+    #[pyo3(text_signature = "($self)")]
+    pub fn try_next(&self) -> PyResult<Option<PyVolumeChangeEvent>> {
+        self.rx
+            .try_recv()
+            .ok()
+            .map(|val| Self::event(self.device.clone(), self.in_db, val))
+            .transpose()
+    }
+
+    /// Return all the events that are already waiting, without waiting for more
+    ///
+    /// :rtype: list[VolumeChangeEvent]
+    #[pyo3(text_signature = "($self)")]
+    pub fn drain(&self) -> PyResult<Vec<PyVolumeChangeEvent>> {
+        std::iter::from_fn(|| self.try_next().transpose()).collect()
+    }
+}
+
+impl Drop for AudioDeviceEventIterator {
+    // When the iterator goes out of scope, stop listening for changes. This doesn't take the GIL,
+    // which may not be available when the interpreter is shutting down.
+    fn drop(&mut self) {
+        self.stop()
+    }
+}
+
+#[pyclass(module = "windows_audio_control")]
+/// Async iterator of changes to a device's mix format, found by polling
+struct MixFormatEventsIterator {
+    device: Py<PyAudioDevice>,
+    interval: Duration,
+    // The format most recently seen, shared with the future of the pending `__anext__`
+    last: Arc<Mutex<format::MixFormat>>,
+}
+
+#[pymethods]
+impl MixFormatEventsIterator {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// :rtype: tuple[int, int, int]
+    ///
+    pub fn __anext__<'a>(&'a mut self, py: Python<'a>) -> PyResult<Option<&'a PyAny>> {
+        let device = self.device.clone_ref(py);
+        let interval = self.interval;
+        let last = self.last.clone();
+        let fut = pyo3_asyncio::async_std::future_into_py(py, async move {
+            loop {
+                task::sleep(interval).await;
+                // This runs on an executor thread, which might not have been used for COM yet
+                com::com_initialized();
+                let format = Python::with_gil(|py| device.borrow(py).0.mix_format())
+                    .map_err(WindowsAudioError::from)?;
+
+                let mut last = last.lock().unwrap();
+                if *last != format {
+                    let new = (format.sample_rate, format.channels, format.bits_per_sample);
+                    *last = format;
+                    return Ok(new);
+                }
+            }
+        })?;
+        Ok(Some(fut))
+    }
+}
+
+#[pyclass(module = "windows_audio_control", name = "AudioDevice", subclass)]
+struct PyAudioDevice(device::AudioDevice);
+
+impl PyAudioDevice {
+    /// The enumerator of the collection this device came from, to listen for its notifications
+    /// through
+    fn enumerator(&self) -> Result<Arc<dyn DeviceSource>> {
+        let enumerator = self
+            .0
+            .enumerator()
+            .context("The device doesn't belong to a DeviceCollection")?;
+        Ok(Arc::new(enumerator.clone()))
+    }
+}
+
+#[pymethods]
+impl PyAudioDevice {
+    #[pyo3(text_signature = "($self)")]
+    pub fn toggle_mute(&self) -> Result<()> {
+        self.0.toggle_mute()?;
+        Ok(())
+    }
+
+    /// Mute or unmute the device
+    ///
+    /// `context` is a GUID string that the resulting VolumeChangeEvent carries in its ``context``
+    /// attribute, so a listener can tell which part of an application made the change. Without
+    /// it, the change is tagged with ``LIBRARY_CONTEXT``.
+    ///
+    /// :type muted: bool
+    /// :type context: str | None
+    #[pyo3(text_signature = "($self, muted, context = None)")]
+    pub fn set_mute(&self, muted: bool, context: Option<&str>) -> PyResult<()> {
+        let context = context
+            .map(property::parse_guid)
+            .transpose()
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        self.0
+            .set_mute_with_context(muted, context.as_ref())
+            .map_err(WindowsAudioError::from)?;
+        Ok(())
+    }
+
+    /// :rtype: str
+    ///
+    /// Device name
+    #[getter]
+    pub fn name(&self) -> Result<&String> {
+        Ok(&self.0.friendly_name)
+    }
+
+    /// :rtype: str
+    #[getter]
+    pub fn device_id(&self) -> Result<&String> {
+        Ok(&self.0.id)
+    }
+
+    /// Whether this is an output (RENDER) or input (CAPTURE) device
+    ///
+    /// :rtype: DataFlow
+    #[getter]
+    pub fn data_flow(&self) -> Result<enums::DataFlow> {
+        Ok(self.0.data_flow()?)
+    }
+
+    /// Whether this is an input (CAPTURE) device
+    ///
+    /// :rtype: bool
+    #[pyo3(text_signature = "($self)")]
+    pub fn is_input(&self) -> Result<bool> {
+        Ok(self.0.data_flow()? == enums::DataFlow::Capture)
+    }
+
+    /// Whether this is an output (RENDER) device
+    ///
+    /// :rtype: bool
+    #[pyo3(text_signature = "($self)")]
+    pub fn is_output(&self) -> Result<bool> {
+        Ok(self.0.data_flow()? == enums::DataFlow::Render)
+    }
+
+    /// The GUID part of the device id, which unlike the whole id doesn't include the data flow
+    ///
+    /// None if the id isn't in the usual ``{0.0.0.00000000}.{GUID}`` form.
+    ///
+    /// :rtype: str | None
+    #[getter]
+    pub fn id_guid(&self) -> Option<&str> {
+        device::split_device_id(&self.0.id).map(|(_, guid)| guid)
+    }
+
+    /// The prefix of the device id before the GUID: ``{0.0.0.00000000}`` for output devices and
+    /// ``{0.0.1.00000000}`` for input devices
+    ///
+    /// None if the id isn't in the usual ``{0.0.0.00000000}.{GUID}`` form.
+    ///
+    /// :rtype: str | None
+    #[getter]
+    pub fn id_prefix(&self) -> Option<&str> {
+        device::split_device_id(&self.0.id).map(|(prefix, _)| prefix)
+    }
+
+    /// Id of the physical device (e.g. a headset) this endpoint is part of
+    ///
+    /// Endpoints of the same physical device, such as a headset's speakers and microphone, share
+    /// a container id.
+    ///
+    /// :rtype: str | None
+    #[getter]
+    pub fn container_id(&self) -> Result<Option<String>> {
+        Ok(self
+            .0
+            .container_id()?
+            .map(|guid| property::format_guid(&guid)))
+    }
+
+    /// The endpoint's GUID as the device reports it in its properties, or None if it doesn't
+    ///
+    /// Other audio APIs (such as DirectSound) identify the endpoint by this GUID, and it appears
+    /// in registry settings that refer to the endpoint, so it can be used to correlate them.
+    /// It isn't necessarily the same as ``id_guid``.
+    ///
+    /// :rtype: str | None
+    #[getter]
+    pub fn endpoint_guid(&self) -> Result<Option<String>> {
+        Ok(self
+            .0
+            .endpoint_guid()?
+            .map(|guid| property::format_guid(&guid)))
+    }
+
+    /// The name of the bus driver the device is attached through, such as ``USB``, ``BTHENUM``
+    /// (Bluetooth) or ``HDAUDIO`` (built-in), or None if not known
+    ///
+    /// :rtype: str | None
+    #[getter]
+    pub fn enumerator_name(&self) -> Result<Option<String>> {
+        Ok(self.0.enumerator_name()?)
+    }
+
+    /// Whether applications may take exclusive control of the device, as on the "Advanced" tab
+    /// of its Sound control panel properties, or None if the device doesn't say
+    ///
+    /// :rtype: bool | None
+    #[getter]
+    pub fn exclusive_mode_allowed(&self) -> Result<Option<bool>> {
+        Ok(self.0.exclusive_mode_allowed()?)
+    }
+
+    /// Whether the driver supports event-driven streams, as exclusive-mode applications usually
+    /// want, or None if the device doesn't say
+    ///
+    /// :rtype: bool | None
+    #[getter]
+    pub fn supports_event_driven_mode(&self) -> Result<Option<bool>> {
+        Ok(self.0.supports_event_driven_mode()?)
+    }
+
+    /// Whether the device's audio enhancements are turned on, as on the "Enhancements" tab of its
+    /// Sound control panel properties
+    ///
+    /// :rtype: bool
+    #[getter]
+    pub fn enhancements_enabled(&self) -> Result<bool> {
+        Ok(self.0.enhancements_enabled()?)
+    }
+
+    /// Turn the device's audio enhancements on or off
+    ///
+    /// Windows only allows this for administrators; otherwise PermissionError is raised.
+    ///
+    /// :type enabled: bool
+    #[pyo3(text_signature = "($self, enabled)")]
+    pub fn set_enhancements_enabled(&self, enabled: bool) -> PyResult<()> {
+        match self.0.set_enhancements_enabled(enabled) {
+            Ok(()) => Ok(()),
+            Err(e) if e.code() == E_ACCESSDENIED => Err(PyPermissionError::new_err(
+                "changing audio enhancements needs administrator rights",
+            )),
+            Err(e) => Err(WindowsAudioError::from(e).into()),
+        }
+    }
+
+    /// Read a value from the device's property store
+    ///
+    /// `fmtid` is the property key's GUID, as a ``{XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX}`` string.
+    /// Integer, boolean, string, GUID (as a string) and date values are supported. None is
+    /// returned if the device doesn't have the property.
+    ///
+    /// :type fmtid: str
+    /// :type pid: int
+    /// :rtype: int | bool | str | datetime.datetime | None
+    #[pyo3(text_signature = "($self, fmtid, pid)")]
+    pub fn get_property(&self, py: Python, fmtid: &str, pid: u32) -> Result<PyObject> {
+        let fmtid =
+            property::parse_guid(fmtid).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let key = PROPERTYKEY { fmtid, pid };
+        Ok(self.0.get_property(&key)?.into_py(py))
+    }
+
+    /// A small, JSON-serializable reference to this device, to find it again later
+    ///
+    /// See ``DeviceCollection.from_token``
+    ///
+    /// :rtype: dict[str, str]
+    #[pyo3(text_signature = "($self)")]
+    pub fn to_token<'a>(&self, py: Python<'a>) -> Result<&'a PyDict> {
+        let token = PyDict::new(py);
+        token.set_item("id", &self.0.id)?;
+        token.set_item("name", &self.0.friendly_name)?;
+        token.set_item("data_flow", self.0.data_flow()?.py_name())?;
+        Ok(token)
+    }
+
+    /// Positions of the physical speakers the device advertises (FrontLeft, FrontRight, LFE etc.)
+    ///
+    /// :rtype: list[str]
+    #[getter]
+    pub fn physical_speakers(&self) -> Result<Vec<&'static str>> {
+        Ok(device::speaker_positions(self.0.physical_speakers()?))
+    }
+
+    /// Number of channels the device has
+    ///
+    /// :rtype: int
+    #[getter]
+    pub fn channel_count(&self) -> Result<u32> {
+        Ok(self.0.channel_count()?)
+    }
+
+    pub fn __repr__(&self) -> Result<String> {
+        Ok(format!(
+            "<AudioDevice name='{}', id='{}'>",
+            self.0.friendly_name, self.0.id,
+        ))
+    }
+
+    #[getter]
+    ///Asyncronoysly yield the events for this device (volume change etc)
+    ///
+    /// :rtype: AudioDeviceEventIterator
+    pub fn events(slf: Py<Self>, py: Python<'_>) -> Result<AudioDeviceEventIterator> {
+        Self::volume_events(
+            slf.as_ref(py),
+            None,
+            false,
+            None,
+            false,
+            false,
+            "scalar",
+            0,
+            false,
+        )
+    }
+
+    /// Asyncronoysly yield the volume change events for this device, with options
+    ///
+    /// With `debounce_ms`, a burst of changes (such as from turning a volume wheel) is coalesced
+    /// into a single event with the final value, once there have been no changes for that long.
+    ///
+    /// With `emit_initial`, the device's current volume and mute state is yielded as the first
+    /// event, before anything has changed.
+    ///
+    /// `policy` decides what happens to a change when the previous one hasn't been consumed yet:
+    /// BLOCK (the default) delivers every event in order, while real-time consumers that only care about the
+    /// latest state can use DROP_OLDEST.
+    ///
+    /// With `dedupe`, an event with the same mute, volume and channel volumes as the one
+    /// immediately before it is dropped, as Windows sometimes notifies the same change more than
+    /// once.
+    ///
+    /// With `master_only`, events only carry the master volume and mute: ``channel_volumes`` is
+    /// left empty (so ``channel_count`` is 0 and ``balance`` None), which saves copying the channel
+    /// volumes of every change on devices with many channels.
+    ///
+    /// `unit` is either "scalar" (0.0 to 1.0) or "db", in which case each event's ``volume`` is the
+    /// level in dB that the endpoint reports for it, the same as its ``volume_db``. If the device
+    /// can't report that level for a change, reading that event raises OSError.
+    ///
+    /// With `min_interval_ms`, at most one change is passed on per that many milliseconds: changes
+    /// arriving sooner are coalesced into the latest, which follows once the interval is up. This
+    /// protects against drivers that notify extremely rapidly while a slider is dragged. Unlike
+    /// `debounce_ms`, changes keep coming during a burst.
+    ///
+    /// With `with_db`, each event's ``volume_db`` is filled in with the level the endpoint reports
+    /// for it, which costs a call to the device per event; otherwise it is None.
+    ///
+    /// :type debounce_ms: int | None
+    /// :type emit_initial: bool
+    /// :type policy: SendPolicy | None
+    /// :type dedupe: bool
+    /// :type master_only: bool
+    /// :type unit: str
+    /// :type min_interval_ms: int
+    /// :type with_db: bool
+    /// :rtype: AudioDeviceEventIterator
+    #[pyo3(
+        text_signature = "($self, debounce_ms = None, emit_initial = False, policy = None, dedupe = False, master_only = False, unit = \"scalar\", min_interval_ms = 0, with_db = False)"
+    )]
+    #[args(
+        emit_initial = "false",
+        dedupe = "false",
+        master_only = "false",
+        unit = "\"scalar\"",
+        min_interval_ms = "0",
+        with_db = "false"
+    )]
+    #[allow(clippy::too_many_arguments)]
+    pub fn volume_events(
+        slf: &PyCell<Self>,
+        debounce_ms: Option<u64>,
+        emit_initial: bool,
+        policy: Option<enums::SendPolicy>,
+        dedupe: bool,
+        master_only: bool,
+        unit: &str,
+        min_interval_ms: u64,
+        with_db: bool,
+    ) -> Result<AudioDeviceEventIterator> {
+        let in_db = match unit {
+            "scalar" => false,
+            "db" => true,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "unknown unit {:?}, expected \"scalar\" or \"db\"",
+                    unit
+                ))
+                .into())
+            }
+        };
+
+        let (tx, rx) = bounded(1);
+
+        // Build the pipeline from the iterator's end backwards: each stage feeds the one after it
+        let (mut stage_tx, mut stage_rx) = (tx, rx.clone());
+        if dedupe {
+            let (raw_tx, raw_rx) = bounded(1);
+            task::spawn(drain::dedupe(
+                raw_rx.clone(),
+                stage_tx,
+                |event: &device::VolumeChangeEvent| Some(event.state()),
+            ));
+            (stage_tx, stage_rx) = (raw_tx, raw_rx);
+        }
+        if let Some(ms) = debounce_ms {
+            let (raw_tx, raw_rx) = bounded(1);
+            task::spawn(drain::debounce(
+                raw_rx.clone(),
+                stage_tx,
+                Duration::from_millis(ms),
+            ));
+            (stage_tx, stage_rx) = (raw_tx, raw_rx);
+        }
+
+        let policy = policy.unwrap_or(enums::SendPolicy::Block);
+        let sender = drain::DrainSender::with_policy(stage_tx, stage_rx, policy);
+        slf.borrow_mut().0.register_volume_change(
+            sender,
+            emit_initial,
+            !master_only,
+            with_db || in_db,
+            Duration::from_millis(min_interval_ms),
+        )?;
+        Ok(AudioDeviceEventIterator {
+            rx,
+            listener: slf.borrow().0.volume_listener(),
+            device: slf.into(),
+            in_db,
+            stopped: false,
+        })
+    }
+
+    /// Activate a COM interface on this device that this library doesn't wrap itself
+    ///
+    /// Only these interfaces are supported: IAudioEndpointVolume, IAudioMeterInformation,
+    /// IAudioSessionManager2 and IAudioClient.
+    ///
+    /// :type interface: str
+    /// :rtype: ActivatedInterface
+    #[pyo3(text_signature = "($self, interface)")]
+    pub fn activate(&self, interface: &str) -> Result<ActivatedInterface> {
+        Ok(ActivatedInterface {
+            name: interface.to_string(),
+            interface: self.0.activate(interface)?,
+        })
+    }
+
+    /// :rtype: DeviceNotificationsIterator
+    ///
+    /// Asynchronously yield the PROPERTY_VALUE_CHANGED events for this device (name or icon changed etc)
+    #[getter]
+    pub fn property_events(&self) -> Result<DeviceNotificationsIterator> {
+        DeviceNotificationsIterator::new(
+            self.enumerator()?,
+            &self.0.id,
+            DeviceCollectionEventType::PropertyValueChanged,
+        )
+    }
+
+    /// Asynchronously yield this device's new DeviceState each time it changes (e.g. to UNPLUGGED
+    /// or DISABLED), without the rest of the collection's events
+    ///
+    /// The events are STATE_CHANGED DeviceCollectionEvents, with the new state in ``state``.
+    ///
+    /// :rtype: DeviceNotificationsIterator
+    #[pyo3(text_signature = "($self)")]
+    pub fn state_events(&self) -> Result<DeviceNotificationsIterator> {
+        DeviceNotificationsIterator::new(
+            self.enumerator()?,
+            &self.0.id,
+            DeviceCollectionEventType::StateChanged,
+        )
+    }
+
+    /// The application audio sessions (of any state) on this device
+    ///
+    /// :rtype: list[AudioSession]
+    #[pyo3(text_signature = "($self)")]
+    pub fn sessions(slf: &PyCell<Self>) -> Result<Vec<PyAudioSession>> {
+        let device: Py<Self> = slf.into();
+        Ok(slf
+            .borrow()
+            .0
+            .sessions()?
+            .into_iter()
+            .map(|session| PyAudioSession {
+                session,
+                device: device.clone(),
+            })
+            .collect())
+    }
+
+    /// The active application audio session on this device with the highest peak level right
+    /// now, i.e. what is making the most noise, or None if no session is active
+    ///
+    /// :rtype: AudioSession | None
+    #[pyo3(text_signature = "($self)")]
+    pub fn loudest_session(slf: &PyCell<Self>) -> Result<Option<PyAudioSession>> {
+        let session = slf.borrow().0.loudest_session()?;
+        Ok(session.map(|session| PyAudioSession {
+            session,
+            device: slf.into(),
+        }))
+    }
+
+    /// Number of application audio sessions that are currently active on this device
+    ///
+    /// :rtype: int
+    #[pyo3(text_signature = "($self)")]
+    pub fn active_session_count(&self) -> Result<usize> {
+        Ok(self.0.active_session_count()?)
+    }
+
+    /// The loudest peak level (0.0 to 1.0) of any application session on this device
+    ///
+    /// This is the level the applications are producing, before the endpoint volume and mute are
+    /// applied -- compare with ``open_meter().peak_value()`` which measures the endpoint itself.
+    ///
+    /// :rtype: float
+    #[pyo3(text_signature = "($self)")]
+    pub fn aggregate_peak(&self) -> Result<f32> {
+        Ok(self.0.aggregate_peak()?)
+    }
+
+    /// Whether audio is currently playing on (or recording from) this device
+    ///
+    /// By default this checks whether any application's audio session on the device is active.
+    /// Sessions can be active while only playing silence, so if `threshold` is given the device's
+    /// peak meter is checked against it instead, e.g. ``is_playing(threshold=0.01)``.
+    ///
+    /// :type threshold: float | None
+    /// :rtype: bool
+    #[pyo3(text_signature = "($self, threshold = None)")]
+    pub fn is_playing(&self, threshold: Option<f32>) -> Result<bool> {
+        match threshold {
+            Some(threshold) => Ok(self.0.open_meter()?.peak_value()? > threshold),
+            None => {
+                for session in self.0.sessions()? {
+                    if session.is_active()? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    /// The sample format the audio engine mixes this device's shared-mode streams in
+    ///
+    /// :rtype: MixFormat
+    #[pyo3(text_signature = "($self)")]
+    pub fn mix_format(&self) -> Result<PyMixFormat> {
+        Ok(self.0.mix_format()?.into())
+    }
+
+    /// Asynchronously yield the device's new ``(sample_rate, channels, bits_per_sample)`` each
+    /// time its mix format changes
+    ///
+    /// Windows doesn't notify changes of format (such as a driver switching from 44.1 to 48 kHz),
+    /// so the format is read every `interval_ms` milliseconds and compared with the last one seen.
+    ///
+    /// :type interval_ms: int
+    /// :rtype: MixFormatEventsIterator
+    #[pyo3(text_signature = "($self, interval_ms = 1000)")]
+    #[args(interval_ms = "1000")]
+    pub fn mix_format_events(
+        slf: &PyCell<Self>,
+        interval_ms: u64,
+    ) -> Result<MixFormatEventsIterator> {
+        let format = slf.borrow().0.mix_format()?;
+        Ok(MixFormatEventsIterator {
+            device: slf.into(),
+            interval: Duration::from_millis(interval_ms),
+            last: Arc::new(Mutex::new(format)),
+        })
+    }
+
+    /// The roles for which this device is currently the default (among devices of its data flow)
+    ///
+    /// :rtype: list[Role]
+    #[pyo3(text_signature = "($self)")]
+    pub fn default_roles(&self) -> Result<Vec<enums::Role>> {
+        let enumerator = collection::DeviceEnumerator::new()?;
+        Ok(source::default_roles(
+            &enumerator,
+            &self.0.id,
+            self.0.data_flow()?,
+        )?)
+    }
+
+    /// Keep volume changes made through this ``AudioDevice`` object between `min` and `max`
+    ///
+    /// The limits are advisory: they apply to ``volume()``'s setters and steps and to
+    /// ``with_volume``, but other applications, the volume keys and other ``AudioDevice`` objects
+    /// for the same device can still go past them.
+    ///
+    /// :type min: float
+    /// :type max: float
+    #[pyo3(text_signature = "($self, min, max)")]
+    pub fn set_volume_limits(&mut self, min: f32, max: f32) -> PyResult<()> {
+        if !(0.0 <= min && min <= max && max <= 1.0) {
+            return Err(PyValueError::new_err(format!(
+                "volume limits {} to {} are not an ascending range within 0.0 to 1.0",
+                min, max
+            )));
+        }
+        self.0
+            .set_volume_limits(Some(device::VolumeLimits { min, max }));
+        Ok(())
+    }
+
+    /// Remove the limits set by ``set_volume_limits``
+    #[pyo3(text_signature = "($self)")]
+    pub fn clear_volume_limits(&mut self) {
+        self.0.set_volume_limits(None);
+    }
+
+    /// The ``(min, max)`` set by ``set_volume_limits``, or None
+    ///
+    /// :rtype: tuple[float, float] | None
+    #[getter]
+    pub fn volume_limits(&self) -> Option<(f32, f32)> {
+        self.0
+            .volume_limits()
+            .map(|limits| (limits.min, limits.max))
+    }
+
+    /// Open the volume control of this device, for several volume operations in a row
+    ///
+    /// :rtype: EndpointVolume
+    #[pyo3(text_signature = "($self)")]
+    pub fn volume(&self) -> Result<PyEndpointVolume> {
+        Ok(PyEndpointVolume(self.0.endpoint_volume()?))
     }
-}
 
-#[pyclass(
-    module = "windows_audio_control",
-    name = "AudioDevice",
-    subclass,
-    unsendable
-)]
-struct PyAudioDevice(device::AudioDevice);
+    /// Set this device's volume for the duration of an ``async with`` block
+    ///
+    /// ``async with device.with_volume(0.5):`` sets the volume to `level` (0.0 to 1.0) on entering
+    /// the block and puts back the volume it had before on leaving it, even if the block raises.
+    ///
+    /// :type level: float
+    /// :rtype: ScopedVolume
+    #[pyo3(text_signature = "($self, level)")]
+    pub fn with_volume(slf: &PyCell<Self>, level: f32) -> PyResult<ScopedVolume> {
+        if !(0.0..=1.0).contains(&level) {
+            return Err(PyValueError::new_err(format!(
+                "volume level {} is not between 0.0 and 1.0",
+                level
+            )));
+        }
+        Ok(ScopedVolume {
+            device: slf.into(),
+            level,
+            previous: None,
+        })
+    }
 
-#[pymethods]
-impl PyAudioDevice {
+    /// Open this device's volume control and peak meter together, to be polled for levels
+    ///
+    /// :rtype: LevelPoller
     #[pyo3(text_signature = "($self)")]
-    pub fn toggle_mute(&self) -> Result<()> {
-        self.0.toggle_mute()?;
-        Ok(())
+    pub fn level_poller(&self) -> Result<PyLevelPoller> {
+        Ok(PyLevelPoller(self.0.level_poller()?))
     }
 
-    /// :rtype: str
+    /// Whether this device's peak meter can be read
     ///
-    /// Device name
-    #[getter]
-    pub fn name(&self) -> Result<&String> {
-        Ok(&self.0.friendly_name)
+    /// Some virtual endpoints don't support metering, in which case ``open_meter()`` or polling
+    /// the meter raises; this lets a UI leave the meter out instead.
+    ///
+    /// :rtype: bool
+    #[pyo3(text_signature = "($self)")]
+    pub fn supports_metering(&self) -> bool {
+        self.0.supports_metering()
     }
 
-    /// :rtype: str
-    #[getter]
-    pub fn device_id(&self) -> Result<&String> {
-        Ok(&self.0.id)
+    /// Open the peak meter of this device, to be polled for levels
+    ///
+    /// With `hold_ms`, the meter's ``levels()`` also reports a peak-hold value: the highest recent
+    /// peak, held for that long and then falling away over the same time again.
+    ///
+    /// :type hold_ms: int | None
+    /// :rtype: Meter
+    #[pyo3(text_signature = "($self, hold_ms = None)")]
+    pub fn open_meter(&self, hold_ms: Option<u64>) -> Result<PyMeter> {
+        let hold = hold_ms.map(|ms| device::PeakHold::new(Duration::from_millis(ms)));
+        Ok(PyMeter(self.0.open_meter()?, hold))
     }
 
-    pub fn __repr__(&self) -> Result<String> {
-        Ok(format!(
-            "<AudioDevice name='{}', id='{}'>",
-            self.0.friendly_name, self.0.id,
-        ))
+    /// Mute this device for `duration_ms` milliseconds, then put back the mute state it had before
+    ///
+    /// The previous state is also put back if the returned awaitable is cancelled.
+    ///
+    /// :type duration_ms: int
+    /// :rtype: typing.Awaitable[None]
+    #[pyo3(text_signature = "($self, duration_ms)")]
+    pub fn mute_for<'a>(
+        slf: &'a PyCell<Self>,
+        py: Python<'a>,
+        duration_ms: u64,
+    ) -> Result<&'a PyAny> {
+        let restore = {
+            let this = slf.borrow();
+            let muted = this.0.volume_state()?.mute;
+            this.0.set_mute(true)?;
+            RestoreMute {
+                device: slf.into(),
+                muted,
+            }
+        };
+
+        Ok(pyo3_asyncio::async_std::future_into_py(py, async move {
+            task::sleep(Duration::from_millis(duration_ms)).await;
+            drop(restore);
+            Ok(())
+        })?)
     }
 
-    #[getter]
-    ///Asyncronoysly yield the events for this device (volume change etc)
+    /// Wait for a volume change of this device for which `predicate(event)` is true
     ///
-    /// :rtype: AudioDeviceEventIterator
-    pub fn events(slf: Py<Self>, py: Python<'_>) -> Result<AudioDeviceEventIterator> {
+    /// Resolves to the first matching VolumeChangeEvent, or None if there wasn't one within
+    /// `timeout_ms` milliseconds. This uses a listener of its own, so any ``events`` or
+    /// ``volume_events`` iterator on this device keeps receiving changes while it waits.
+    ///
+    /// :type predicate: typing.Callable[[VolumeChangeEvent], bool]
+    /// :type timeout_ms: int
+    /// :rtype: typing.Awaitable[typing.Optional[VolumeChangeEvent]]
+    #[pyo3(text_signature = "($self, predicate, timeout_ms)")]
+    pub fn wait_for_volume<'a>(
+        slf: &'a PyCell<Self>,
+        py: Python<'a>,
+        predicate: PyObject,
+        timeout_ms: u64,
+    ) -> Result<&'a PyAny> {
         let (tx, rx) = bounded(1);
-        slf.borrow_mut(py).0.register_volume_change(tx)?;
-        Ok(AudioDeviceEventIterator { rx, device: slf })
+        let listener = slf.borrow().0.listen_volume_change(
+            drain::DrainSender::new(tx),
+            true,
+            false,
+            Duration::ZERO,
+        )?;
+        let listening = StopListening {
+            device: slf.into(),
+            listener,
+        };
+
+        Ok(pyo3_asyncio::async_std::future_into_py(py, async move {
+            let matching = async {
+                while let Ok(event) = rx.recv().await {
+                    let pyevent = PyVolumeChangeEvent::new(listening.device.clone(), event);
+                    let found = Python::with_gil(|py| -> PyResult<_> {
+                        let pyevent = Py::new(py, pyevent)?;
+                        let matched = predicate.call1(py, (pyevent.clone_ref(py),))?;
+                        Ok(matched.is_true(py)?.then_some(pyevent))
+                    })?;
+                    if found.is_some() {
+                        return Ok(found);
+                    }
+                }
+                PyResult::Ok(None)
+            };
+            let found = match future::timeout(Duration::from_millis(timeout_ms), matching).await {
+                Ok(found) => found?,
+                Err(_) => None,
+            };
+            drop(listening);
+            Ok(found)
+        })?)
     }
 
     /// Make this device the default for the specified role
@@ -476,10 +3022,46 @@ impl PyAudioDevice {
         Ok(())
     }
 
-    fn __richcmp__(&self, other: &Self, op: CompareOp, py: Python<'_>) -> PyObject {
+    /// Make this device the default for every role (console, multimedia and communications)
+    ///
+    /// A role that can't be set doesn't stop the others. Returns a ``(role, ok, error_message)``
+    /// tuple for each role, where `role` is the name of the Role.
+    ///
+    /// :rtype: list[tuple[str, bool, typing.Optional[str]]]
+    #[pyo3(text_signature = "($self)")]
+    pub fn set_default_all_roles(&self) -> BatchResult {
+        let mut outcome = BatchResult::default();
+        for role in enums::Role::ALL {
+            outcome.push(role.py_name().to_string(), self.0.set_default(role.into()));
+        }
+        outcome
+    }
+
+    /// Whether a DeviceCollectionEvent is about this device
+    ///
+    /// The ids are compared after ``normalize_device_id``, so differences of case or surrounding
+    /// whitespace don't matter.
+    ///
+    /// :type event: DeviceCollectionEvent
+    /// :rtype: bool
+    #[pyo3(text_signature = "($self, event)")]
+    pub fn matches_event(&self, event: &PyDeviceCollectionEvent) -> bool {
+        device::normalize_device_id(&event.device_id) == device::normalize_device_id(&self.0.id)
+    }
+
+    /// Devices compare equal to other devices, and to strings, by device id
+    fn __richcmp__(&self, other: &PyAny, op: CompareOp, py: Python<'_>) -> PyObject {
+        let equal = if let Ok(other) = other.extract::<PyRef<Self>>() {
+            self.eq(&other)
+        } else if let Ok(other) = other.extract::<&str>() {
+            self.0.id == other
+        } else {
+            return py.NotImplemented();
+        };
+
         match op {
-            CompareOp::Eq => self.eq(other).into_py(py),
-            CompareOp::Ne => self.ne(other).into_py(py),
+            CompareOp::Eq => equal.into_py(py),
+            CompareOp::Ne => (!equal).into_py(py),
             _ => py.NotImplemented(),
         }
     }
@@ -491,6 +3073,378 @@ impl PartialEq for PyAudioDevice {
     }
 }
 
+#[pyclass(module = "windows_audio_control", unsendable)]
+/// A COM interface activated on a device, for use with ctypes or comtypes
+struct ActivatedInterface {
+    /// :rtype: str
+    #[pyo3(get)]
+    name: String,
+    interface: IUnknown,
+}
+
+#[pymethods]
+impl ActivatedInterface {
+    /// The raw interface pointer
+    ///
+    /// It is only valid for as long as this object is alive.
+    ///
+    /// :rtype: int
+    #[getter]
+    pub fn address(&self) -> usize {
+        self.interface.as_raw() as usize
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!(
+            "<ActivatedInterface name='{}' address={:#x}>",
+            self.name,
+            self.address()
+        )
+    }
+}
+
+/// Stops a volume listener when dropped, whether or not the wait completed
+struct StopListening {
+    device: Py<PyAudioDevice>,
+    listener: device::VolumeListener,
+}
+
+impl Drop for StopListening {
+    fn drop(&mut self) {
+        // This may run on an executor thread, which might not have been used for COM yet
+        com::com_initialized();
+        self.listener.stop();
+    }
+}
+
+/// Puts the mute state of a device back when dropped, whether or not the sleep completed
+struct RestoreMute {
+    device: Py<PyAudioDevice>,
+    muted: bool,
+}
+
+impl Drop for RestoreMute {
+    fn drop(&mut self) {
+        // This may run on an executor thread, which might not have been used for COM yet
+        com::com_initialized();
+        Python::with_gil(|py| {
+            if let Err(err) = self.device.borrow(py).0.set_mute(self.muted) {
+                warn!("Unable to restore mute state: {}", err);
+            }
+        });
+    }
+}
+
+#[pyclass(module = "windows_audio_control", name = "MixFormat")]
+/// The sample format of a device's shared-mode mix
+struct PyMixFormat {
+    /// :rtype: int
+    #[pyo3(get)]
+    channels: u16,
+
+    /// :rtype: int
+    #[pyo3(get)]
+    sample_rate: u32,
+
+    /// Size of each sample's container, in bits
+    ///
+    /// :rtype: int
+    #[pyo3(get)]
+    bits_per_sample: u16,
+
+    /// Bits of each sample that hold data, e.g. 24 of a 32-bit container
+    ///
+    /// :rtype: int
+    #[pyo3(get)]
+    valid_bits_per_sample: u16,
+
+    /// ``"pcm"`` (integer samples), ``"float"`` or ``"unknown"``
+    ///
+    /// :rtype: str
+    #[pyo3(get)]
+    sample_format: &'static str,
+
+    /// Mask of the speaker positions of the channels, or 0 if the format doesn't say
+    ///
+    /// :rtype: int
+    #[pyo3(get)]
+    channel_mask: u32,
+}
+
+impl From<format::MixFormat> for PyMixFormat {
+    fn from(f: format::MixFormat) -> Self {
+        PyMixFormat {
+            channels: f.channels,
+            sample_rate: f.sample_rate,
+            bits_per_sample: f.bits_per_sample,
+            valid_bits_per_sample: f.valid_bits_per_sample,
+            sample_format: f.sample_format,
+            channel_mask: f.channel_mask,
+        }
+    }
+}
+
+#[pymethods]
+impl PyMixFormat {
+    /// Convert raw audio in this format to float samples in the range -1.0 to 1.0
+    ///
+    /// The samples are returned packed as native-endian float32, ready for ``array('f', ...)`` or
+    /// ``numpy.frombuffer(..., dtype=numpy.float32)``. Channels stay interleaved, as they are in
+    /// `data`. Raises ValueError if the format isn't one that can be converted, or `data` isn't a
+    /// whole number of samples.
+    ///
+    /// :type data: bytes
+    /// :rtype: bytes
+    #[pyo3(text_signature = "($self, data)")]
+    fn to_float32<'py>(&self, py: Python<'py>, data: &[u8]) -> PyResult<&'py PyBytes> {
+        let samples = format::samples_to_f32(self.sample_format, self.bits_per_sample, data)
+            .ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "Can't convert {} bytes of {}-bit {} samples to float32",
+                    data.len(),
+                    self.bits_per_sample,
+                    self.sample_format
+                ))
+            })?;
+        let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_ne_bytes()).collect();
+        Ok(PyBytes::new(py, &bytes))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "<MixFormat {} {}-bit ({} valid) {}Hz channels={} channel_mask={:#x}>",
+            self.sample_format,
+            self.bits_per_sample,
+            self.valid_bits_per_sample,
+            self.sample_rate,
+            self.channels,
+            self.channel_mask,
+        )
+    }
+}
+
+#[pyclass(module = "windows_audio_control", name = "LevelPoller")]
+/// Volume control and peak meter of an audio device, for polling both in a loop
+struct PyLevelPoller(device::LevelPoller);
+
+#[pymethods]
+impl PyLevelPoller {
+    /// Read the master volume, mute state and peak level as ``(volume, mute, peak)``
+    ///
+    /// The GIL is released while Windows is asked, so other Python threads keep running while
+    /// one polls the device in a tight loop.
+    ///
+    /// :rtype: tuple[float, bool, float]
+    #[pyo3(text_signature = "($self)")]
+    pub fn poll(&self, py: Python) -> Result<(f32, bool, f32)> {
+        let poller = &self.0;
+        Ok(py.allow_threads(|| poller.poll())?)
+    }
+}
+
+#[pyclass(module = "windows_audio_control", name = "Meter", unsendable)]
+/// Peak meter of an audio device
+struct PyMeter(device::AudioMeter, Option<device::PeakHold>);
+
+#[pymethods]
+impl PyMeter {
+    /// Peak sample value across all channels, in the range 0.0 to 1.0
+    ///
+    /// :rtype: float
+    #[pyo3(text_signature = "($self)")]
+    pub fn peak_value(&self) -> Result<f32> {
+        Ok(self.0.peak_value()?)
+    }
+
+    /// Peak sample value of each channel, in the range 0.0 to 1.0
+    ///
+    /// :rtype: tuple(float, ...)
+    #[pyo3(text_signature = "($self)")]
+    pub fn channel_peaks<'a>(&self, py: Python<'a>) -> Result<&'a PyTuple> {
+        Ok(PyTuple::new(py, self.0.channel_peaks()?.iter()))
+    }
+
+    /// The current peak value and the peak-hold value, as ``(peak, held)``
+    ///
+    /// The held value only updates when this is called, so poll it at the display's frame rate.
+    /// Without ``hold_ms`` on ``open_meter()``, the held value is just the current peak.
+    ///
+    /// :rtype: tuple[float, float]
+    #[pyo3(text_signature = "($self)")]
+    pub fn levels(&mut self) -> Result<(f32, f32)> {
+        let peak = self.0.peak_value()?;
+        let held = match self.1.as_mut() {
+            Some(hold) => hold.update(peak, std::time::Instant::now()),
+            None => peak,
+        };
+        Ok((peak, held))
+    }
+}
+
+#[pyclass(module = "windows_audio_control", name = "EndpointVolume", unsendable)]
+/// Volume control of an audio device
+struct PyEndpointVolume(device::EndpointVolume);
+
+#[pymethods]
+impl PyEndpointVolume {
+    /// The master volume, in the range 0.0 to 1.0
+    ///
+    /// :rtype: float
+    #[pyo3(text_signature = "($self)")]
+    pub fn get(&self) -> Result<f32> {
+        Ok(self.0.volume()?)
+    }
+
+    /// Set the master volume, in the range 0.0 to 1.0
+    ///
+    /// :type volume: float
+    #[pyo3(text_signature = "($self, volume)")]
+    pub fn set(&self, volume: f32) -> Result<()> {
+        Ok(self.0.set_volume(volume)?)
+    }
+
+    /// Whether the device is muted
+    ///
+    /// :rtype: bool
+    #[pyo3(text_signature = "($self)")]
+    pub fn get_mute(&self) -> Result<bool> {
+        Ok(self.0.mute()?)
+    }
+
+    /// Mute or unmute the device
+    ///
+    /// :type muted: bool
+    #[pyo3(text_signature = "($self, muted)")]
+    pub fn set_mute(&self, muted: bool) -> Result<()> {
+        Ok(self.0.set_mute(muted)?)
+    }
+
+    /// Raise the volume by one step of the device's own step size
+    #[pyo3(text_signature = "($self)")]
+    pub fn step_up(&self) -> Result<()> {
+        Ok(self.0.step(true)?)
+    }
+
+    /// Lower the volume by one step of the device's own step size
+    #[pyo3(text_signature = "($self)")]
+    pub fn step_down(&self) -> Result<()> {
+        Ok(self.0.step(false)?)
+    }
+
+    /// Number of channels the device has
+    ///
+    /// :rtype: int
+    #[pyo3(text_signature = "($self)")]
+    pub fn channel_count(&self) -> Result<u32> {
+        Ok(self.0.channel_count()?)
+    }
+
+    /// The device's volume range in dB, as ``(min_db, max_db, step_db)``
+    ///
+    /// :rtype: tuple[float, float, float]
+    #[pyo3(text_signature = "($self)")]
+    pub fn range(&self) -> Result<(f32, f32, f32)> {
+        let range = self.0.range()?;
+        Ok((range.min_db, range.max_db, range.step_db))
+    }
+}
+
+#[pyclass(module = "windows_audio_control")]
+/// Async context manager that sets a device's volume for the duration of a block
+struct ScopedVolume {
+    device: Py<PyAudioDevice>,
+    level: f32,
+    // The volume to put back, once the block has been entered
+    previous: Option<f32>,
+}
+
+#[pymethods]
+impl ScopedVolume {
+    /// :rtype: typing.Awaitable[AudioDevice]
+    #[pyo3(text_signature = "($self)")]
+    fn __aenter__<'a>(&mut self, py: Python<'a>) -> Result<&'a PyAny> {
+        let device = self.device.clone_ref(py);
+        {
+            let volume = device.borrow(py).0.endpoint_volume()?;
+            self.previous = Some(volume.volume()?);
+            volume.set_volume(self.level)?;
+        }
+        Ok(pyo3_asyncio::async_std::future_into_py(py, async move {
+            Ok(device)
+        })?)
+    }
+
+    /// :rtype: typing.Awaitable[bool]
+    #[pyo3(text_signature = "($self, exc_type, exc_value, traceback)")]
+    fn __aexit__<'a>(
+        &mut self,
+        py: Python<'a>,
+        _exc_type: &PyAny,
+        _exc_value: &PyAny,
+        _traceback: &PyAny,
+    ) -> Result<&'a PyAny> {
+        if let Some(previous) = self.previous.take() {
+            let device = self.device.borrow(py);
+            device.0.endpoint_volume()?.set_volume(previous)?;
+        }
+        Ok(pyo3_asyncio::async_std::future_into_py(py, async move {
+            Ok(false)
+        })?)
+    }
+}
+
+#[pyclass(module = "windows_audio_control", name = "AudioSession")]
+/// An application's stream playing to or recording from a device
+struct PyAudioSession {
+    session: session::AudioSession,
+
+    /// The device this session belongs to
+    ///
+    /// :rtype: AudioDevice
+    #[pyo3(get)]
+    device: Py<PyAudioDevice>,
+}
+
+#[pymethods]
+impl PyAudioSession {
+    /// The id of the device this session belongs to
+    ///
+    /// :rtype: str
+    #[getter]
+    pub fn device_id(&self, py: Python) -> String {
+        self.device.borrow(py).0.id.clone()
+    }
+
+    /// Whether the session is currently active (has an open, running stream)
+    ///
+    /// :rtype: bool
+    #[pyo3(text_signature = "($self)")]
+    pub fn is_active(&self) -> Result<bool> {
+        Ok(self.session.is_active()?)
+    }
+
+    /// Peak sample value of this session alone, in the range 0.0 to 1.0
+    ///
+    /// :rtype: float
+    #[pyo3(text_signature = "($self)")]
+    pub fn peak_value(&self) -> Result<f32> {
+        Ok(self.session.peak_value()?)
+    }
+}
+
+/// Put a device id in the form Windows itself gives ids in
+///
+/// This removes surrounding whitespace and lower-cases the letters. Lookups by id already do this,
+/// so use it to compare ids from elsewhere (e.g. config files) against ``AudioDevice.device_id``.
+///
+/// :type device_id: str
+/// :rtype: str
+#[pyfunction]
+#[pyo3(text_signature = "(device_id)")]
+fn normalize_device_id(device_id: &str) -> String {
+    device::normalize_device_id(device_id)
+}
+
 /// Native implementation
 #[pymodule]
 fn _native(py: Python, m: &PyModule) -> PyResult<()> {
@@ -502,14 +3456,36 @@ fn _native(py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<FilteredDeviceCollection>()?;
     m.add_class::<PyAudioDevice>()?;
     m.add_class::<AudioDeviceEventIterator>()?;
+    m.add_class::<PyAudioSession>()?;
+    m.add_class::<PyLevelPoller>()?;
+    m.add_class::<PyMeter>()?;
+    m.add_class::<PyEndpointVolume>()?;
+    m.add_class::<PyMixFormat>()?;
+    m.add_class::<MixFormatEventsIterator>()?;
+    m.add_class::<ScopedVolume>()?;
+    m.add_class::<ActivatedInterface>()?;
 
     m.add_class::<CollectionEventsIterator>()?;
+    m.add_class::<CollectionCallback>()?;
+    m.add_class::<DefaultChangeEventsIterator>()?;
+    m.add_class::<DefaultOutputEventsIterator>()?;
+    m.add_class::<DefaultSummary>()?;
+    m.add_class::<SystemAudioState>()?;
+    m.add_class::<DeviceNotificationsIterator>()?;
     m.add_class::<DeviceCollectionEventType>()?;
     m.add_class::<PyDeviceCollectionEvent>()?;
     m.add_class::<PyVolumeChangeEvent>()?;
     // m.add_class::<enums::DeviceState>()?;
     m.add_class::<enums::DataFlow>()?;
     m.add_class::<enums::Role>()?;
+    m.add_class::<enums::DuckingPreference>()?;
+    m.add_class::<enums::SendPolicy>()?;
+
+    m.add_function(wrap_pyfunction!(normalize_device_id, m)?)?;
+    m.add(
+        "LIBRARY_CONTEXT",
+        property::format_guid(&device::LIBRARY_CONTEXT),
+    )?;
 
     // IntEnum -- pyo3 doesn't support this yet, so we have to do it ourselves
 
@@ -533,3 +3509,230 @@ fn _native(py: Python, m: &PyModule) -> PyResult<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(windows)]
+    use fake_source::FakeDeviceSource;
+
+    #[test]
+    fn batch_result_keeps_going_past_failures() {
+        let mut outcome = BatchResult::default();
+        outcome.push("first".to_string(), Ok::<_, anyhow::Error>(()));
+        outcome.push("second".to_string(), Err(anyhow::anyhow!("access denied")));
+        outcome.push("third".to_string(), Ok::<_, anyhow::Error>(()));
+
+        assert_eq!(
+            outcome.outcomes(),
+            [
+                ("first".to_string(), true, None),
+                (
+                    "second".to_string(),
+                    false,
+                    Some("access denied".to_string())
+                ),
+                ("third".to_string(), true, None),
+            ]
+        );
+    }
+
+    // The rest use the fake's devices and events, which go through COM (and Python), and those are
+    // only linked on Windows
+
+    #[cfg(windows)]
+    #[test]
+    fn devices_dict_keys_follow_its_dataflow() {
+        let source = Arc::new(FakeDeviceSource::sample());
+        assert_eq!(
+            DevicesDict(source.clone(), enums::DataFlow::Render)
+                .keys()
+                .unwrap(),
+            ["speakers", "headset"]
+        );
+        assert_eq!(
+            DevicesDict(source, enums::DataFlow::All).keys().unwrap(),
+            ["speakers", "headset", "mic"]
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn devices_dict_only_finds_devices_of_its_dataflow() {
+        let source = Arc::new(FakeDeviceSource::sample());
+        let outputs = DevicesDict(source.clone(), enums::DataFlow::Render);
+
+        assert_eq!(outputs.__getitem__("Speakers").unwrap().0.id, "speakers");
+        assert!(outputs.__getitem__("mic").is_err());
+        assert!(outputs.__getitem__("webcam").is_err());
+
+        let all = DevicesDict(source, enums::DataFlow::All);
+        assert_eq!(
+            all.__getitem__("mic").unwrap().0.friendly_name,
+            "Microphone"
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn removed_devices_are_named_from_what_was_known() {
+        let names = DeviceNames(Arc::new(Mutex::new(HashMap::from([(
+            "unplugged".to_string(),
+            "Old headset".to_string(),
+        )]))));
+
+        let event = CollectionEventsIterator::convert(
+            &FakeDeviceSource::sample(),
+            collection::DeviceNotificationEvent::Removed("unplugged".into()),
+            false,
+            Some(&names),
+        )
+        .unwrap();
+        assert_eq!(event.kind, DeviceCollectionEventType::Removed);
+        assert_eq!(event.name.as_deref(), Some("Old headset"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn added_devices_are_named_and_given_their_state() {
+        let source = FakeDeviceSource::sample();
+        let names = DeviceNames::new(&source).unwrap();
+
+        let event = CollectionEventsIterator::convert(
+            &source,
+            collection::DeviceNotificationEvent::Added("mic".into()),
+            false,
+            Some(&names),
+        )
+        .unwrap();
+        assert_eq!(event.name.as_deref(), Some("Microphone"));
+        assert_eq!(event.state, Some(enums::DeviceState::Active));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn new_defaults_are_resolved_unless_already_gone() {
+        pyo3::prepare_freethreaded_python();
+        let source = FakeDeviceSource::sample();
+        let event = |device_id: &str| {
+            collection::DeviceNotificationEvent::DefaultChanged(
+                device_id.into(),
+                enums::DataFlow::Render,
+                enums::Role::Console,
+            )
+        };
+
+        let resolved = CollectionEventsIterator::convert(&source, event("headset"), true, None)
+            .unwrap()
+            .device
+            .unwrap();
+        Python::with_gil(|py| assert_eq!(resolved.borrow(py).0.friendly_name, "Headset"));
+
+        let gone = CollectionEventsIterator::convert(&source, event("webcam"), true, None).unwrap();
+        assert_eq!(gone.device_id, "webcam");
+        assert!(gone.device.is_none());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn collection_events_arrive_through_the_registered_client() {
+        let source = Arc::new(FakeDeviceSource::sample());
+        let (tx, rx) = bounded(1);
+        let client = NotificationClient::new(tx).unwrap();
+        source.register_notification(&client).unwrap();
+        let events = CollectionEventsIterator {
+            enumerator: source.clone(),
+            source: Some(client),
+            rx,
+            resolve_defaults: false,
+            names: None,
+        };
+
+        source
+            .notify(&collection::DeviceNotificationEvent::StateChanged(
+                "headset".into(),
+                enums::DeviceState::Active,
+            ))
+            .unwrap();
+        let event = events.try_next().unwrap().unwrap();
+        assert_eq!(event.kind, DeviceCollectionEventType::StateChanged);
+        assert_eq!(event.device_id, "headset");
+        assert_eq!(event.state, Some(enums::DeviceState::Active));
+        assert!(events.try_next().unwrap().is_none());
+
+        drop(events);
+        assert_eq!(source.client_count(), 0);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn property_events_are_those_of_their_device() {
+        use collection::DeviceNotificationEvent::{PropertyValueChanged, StateChanged};
+        use windows::Win32::Devices::FunctionDiscovery::PKEY_Device_FriendlyName;
+
+        let source = Arc::new(FakeDeviceSource::sample());
+        let events = DeviceNotificationsIterator::new(
+            source.clone(),
+            "SPEAKERS",
+            DeviceCollectionEventType::PropertyValueChanged,
+        )
+        .unwrap();
+        for event in [
+            PropertyValueChanged("mic".into(), PKEY_Device_FriendlyName),
+            StateChanged("speakers".into(), enums::DeviceState::Disabled),
+            PropertyValueChanged("speakers".into(), PKEY_Device_FriendlyName),
+        ] {
+            source.notify(&event).unwrap();
+        }
+
+        let next = || {
+            task::block_on(DeviceNotificationsIterator::next_event(
+                events.rx.clone(),
+                events.device_id.clone(),
+                events.kind.clone(),
+            ))
+        };
+        let event = next().unwrap().unwrap();
+        assert_eq!(event.kind, DeviceCollectionEventType::PropertyValueChanged);
+        assert_eq!(event.device_id, "speakers");
+
+        // Closing the listeners of the collection the device came from ends the iterator too
+        source.close_all().unwrap();
+        assert!(next().is_none());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn state_events_are_the_new_states_of_their_device() {
+        use collection::DeviceNotificationEvent::{Added, StateChanged};
+
+        let source = Arc::new(FakeDeviceSource::sample());
+        let events = DeviceNotificationsIterator::new(
+            source.clone(),
+            "headset",
+            DeviceCollectionEventType::StateChanged,
+        )
+        .unwrap();
+        for event in [
+            Added("headset".into()),
+            StateChanged("speakers".into(), enums::DeviceState::Disabled),
+            StateChanged("headset".into(), enums::DeviceState::Active),
+        ] {
+            source.notify(&event).unwrap();
+        }
+
+        let next = || {
+            task::block_on(DeviceNotificationsIterator::next_event(
+                events.rx.clone(),
+                events.device_id.clone(),
+                events.kind.clone(),
+            ))
+        };
+        let event = next().unwrap().unwrap();
+        assert_eq!(event.device_id, "headset");
+        assert_eq!(event.state, Some(enums::DeviceState::Active));
+
+        source.close_all().unwrap();
+        assert!(next().is_none());
+    }
+}
@@ -0,0 +1,62 @@
+//! Bookkeeping of the callbacks registered through one `DeviceEnumerator`, so they can all be
+//! torn down together.
+
+use std::sync::Mutex;
+
+use windows::{
+    core::{AgileReference, Interface, Result},
+    Win32::Media::Audio::{Endpoints::IAudioEndpointVolumeCallback, IMMNotificationClient},
+};
+
+/// Callbacks of one interface type that are currently registered
+///
+/// Entries are keyed by the address of the interface that was registered, which is also what the
+/// code holding on to the callback has.
+pub struct Registered<T>(Mutex<Vec<(usize, AgileReference<T>)>>);
+
+impl<T> Default for Registered<T> {
+    fn default() -> Self {
+        Registered(Mutex::new(Vec::new()))
+    }
+}
+
+impl<T: Interface + PartialEq> Registered<T> {
+    pub fn insert(&self, interface: &T, agile: AgileReference<T>) {
+        let key = interface.as_raw() as usize;
+        self.0.lock().unwrap().push((key, agile));
+    }
+
+    /// Forget `interface`, returning whether it was still registered
+    pub fn remove(&self, interface: &T) -> bool {
+        let key = interface.as_raw() as usize;
+        self.remove_where(|(k, _)| *k == key)
+    }
+
+    /// Forget the callback `agile` refers to, returning whether it was still registered
+    pub fn remove_reference(&self, agile: &AgileReference<T>) -> bool {
+        self.remove_where(|(_, a)| a == agile)
+    }
+
+    fn remove_where(&self, f: impl Fn(&(usize, AgileReference<T>)) -> bool) -> bool {
+        let mut entries = self.0.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|entry| !f(entry));
+        entries.len() != before
+    }
+
+    /// Forget every callback, returning the ones that can still be reached so they can be
+    /// unregistered
+    pub fn take_all(&self) -> Vec<Result<T>> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+            .into_iter()
+            .map(|(_, agile)| agile.resolve())
+            .collect()
+    }
+}
+
+/// Every listener registered through a `DeviceEnumerator` (or a device it created)
+#[derive(Default)]
+pub struct Listeners {
+    pub notifications: Registered<IMMNotificationClient>,
+    pub volume: Registered<IAudioEndpointVolumeCallback>,
+}
@@ -34,3 +34,29 @@ pub unsafe trait IPolicyConfig: IUnknown {
     // HRESULT STDMETHODCALLTYPE SetEndpointVisibility(PCWSTR, INT);
     // unsafe fn SetEndpointVisibility(&self) -> windows::core::HRESULT;
 }
+
+#[allow(non_upper_case_globals)]
+pub const PolicyConfigVista: windows::core::GUID =
+    ::windows::core::GUID::from_u128(0x294935CE_F637_4E7C_A41B_AB255460B862);
+
+/// The Vista-era version of `IPolicyConfig`, which lacks `ResetDeviceFormat()` and so has
+/// `SetDefaultEndpoint()` one slot earlier
+#[windows::core::interface("568B9108-44BF-40B4-9006-86AFE5B5A620")]
+pub unsafe trait IPolicyConfigVista: IUnknown {
+    fn dummy1(&self) -> ();
+    fn dummy2(&self) -> ();
+    fn dummy3(&self) -> ();
+    fn dummy4(&self) -> ();
+    fn dummy5(&self) -> ();
+    fn dummy6(&self) -> ();
+    fn dummy7(&self) -> ();
+    fn dummy8(&self) -> ();
+    fn dummy9(&self) -> ();
+
+    // HRESULT STDMETHODCALLTYPE SetDefaultEndpoint(__in PCWSTR wszDeviceId, __in ERole role);
+    pub unsafe fn SetDefaultEndpoint(
+        &self,
+        wszDeviceId: windows::core::PCWSTR,
+        role: ERole,
+    ) -> windows::core::HRESULT;
+}
@@ -0,0 +1,157 @@
+//! Typed reading of values from a device's property store.
+
+use anyhow::bail;
+use pyo3::{prelude::*, types::PyDict};
+use windows::{
+    core::GUID,
+    Win32::{
+        Media::Audio::IMMDevice,
+        System::Com::{
+            StructuredStorage::{PropVariantClear, PROPVARIANT},
+            STGM_READ, STGM_READWRITE, VT_BOOL, VT_CLSID, VT_EMPTY, VT_FILETIME, VT_LPWSTR, VT_UI4,
+        },
+        UI::Shell::PropertiesSystem::PROPERTYKEY,
+    },
+};
+
+use super::errors::WindowsAudioError;
+
+/// Number of 100ns FILETIME intervals between 1601-01-01 and the Unix epoch
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    Empty,
+    UInt(u32),
+    Bool(bool),
+    String(String),
+    Guid(GUID),
+    /// 100ns intervals since 1601-01-01 UTC
+    FileTime(u64),
+}
+
+pub fn read_property(device: &IMMDevice, key: &PROPERTYKEY) -> anyhow::Result<PropertyValue> {
+    unsafe {
+        let properties = device.OpenPropertyStore(STGM_READ)?;
+        let mut prop = properties.GetValue(key)?;
+
+        let inner = &prop.Anonymous.Anonymous;
+        let value = match inner.vt {
+            VT_EMPTY => Ok(PropertyValue::Empty),
+            VT_UI4 => Ok(PropertyValue::UInt(inner.Anonymous.ulVal)),
+            // VARIANT_TRUE is -1, VARIANT_FALSE is 0
+            VT_BOOL => Ok(PropertyValue::Bool(inner.Anonymous.boolVal != 0)),
+            VT_LPWSTR => inner
+                .Anonymous
+                .pwszVal
+                .to_string()
+                .map(PropertyValue::String)
+                .map_err(|e| WindowsAudioError::from(e).into()),
+            VT_CLSID => Ok(PropertyValue::Guid(*inner.Anonymous.puuid)),
+            VT_FILETIME => {
+                let ft = inner.Anonymous.filetime;
+                Ok(PropertyValue::FileTime(
+                    (ft.dwHighDateTime as u64) << 32 | ft.dwLowDateTime as u64,
+                ))
+            }
+            vt => Err(anyhow::anyhow!("unsupported property type {}", vt.0)),
+        };
+
+        PropVariantClear(&mut prop)?;
+        value
+    }
+}
+
+/// Store an integer value in the device's property store
+///
+/// Opening the store for writing needs administrator rights, so this fails with `E_ACCESSDENIED`
+/// otherwise.
+pub fn write_uint_property(
+    device: &IMMDevice,
+    key: &PROPERTYKEY,
+    value: u32,
+) -> windows::core::Result<()> {
+    unsafe {
+        let properties = device.OpenPropertyStore(STGM_READWRITE)?;
+
+        let mut prop = PROPVARIANT::default();
+        let inner = &mut prop.Anonymous.Anonymous;
+        inner.vt = VT_UI4;
+        inner.Anonymous.ulVal = value;
+
+        properties.SetValue(key, &prop)?;
+        properties.Commit()
+    }
+}
+
+/// Parse a GUID in the `{XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX}` form (braces optional)
+pub fn parse_guid(text: &str) -> anyhow::Result<GUID> {
+    let trimmed = text.trim();
+    let inner = trimmed
+        .strip_prefix('{')
+        .and_then(|t| t.strip_suffix('}'))
+        .unwrap_or(trimmed);
+
+    let groups: Vec<&str> = inner.split('-').collect();
+    let lengths: Vec<usize> = groups.iter().map(|g| g.len()).collect();
+    if lengths != [8, 4, 4, 4, 12] || !inner.chars().all(|c| c == '-' || c.is_ascii_hexdigit()) {
+        bail!("invalid GUID {:?}", text);
+    }
+
+    Ok(GUID::from_u128(u128::from_str_radix(&groups.concat(), 16)?))
+}
+
+/// Format a GUID in the `{XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX}` form used in the registry
+pub fn format_guid(guid: &GUID) -> String {
+    format!("{{{:?}}}", guid)
+}
+
+impl IntoPy<PyObject> for PropertyValue {
+    fn into_py(self, py: Python) -> PyObject {
+        match self {
+            PropertyValue::Empty => py.None(),
+            PropertyValue::UInt(v) => v.into_py(py),
+            PropertyValue::Bool(v) => v.into_py(py),
+            PropertyValue::String(v) => v.into_py(py),
+            PropertyValue::Guid(v) => format_guid(&v).into_py(py),
+            PropertyValue::FileTime(v) => {
+                let timestamp = (v as f64 - FILETIME_UNIX_EPOCH as f64) / 10_000_000.0;
+                let datetime = || -> PyResult<PyObject> {
+                    let module = py.import("datetime")?;
+                    let kwargs = PyDict::new(py);
+                    kwargs.set_item("tz", module.getattr("timezone")?.getattr("utc")?)?;
+                    Ok(module
+                        .getattr("datetime")?
+                        .call_method("fromtimestamp", (timestamp,), Some(kwargs))?
+                        .into_py(py))
+                };
+                // Dates outside of the range Python can represent are left as the raw FILETIME
+                datetime().unwrap_or_else(|_| v.into_py(py))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guid_round_trip() {
+        let text = "{1DA5D803-D492-4EDD-8C23-E0C0FFEE7F0E}";
+        let guid = parse_guid(text).unwrap();
+        assert_eq!(
+            guid,
+            GUID::from_u128(0x1da5d803_d492_4edd_8c23_e0c0ffee7f0e)
+        );
+        assert_eq!(format_guid(&guid), text);
+        assert_eq!(parse_guid(&text.to_lowercase()[1..37]).unwrap(), guid);
+    }
+
+    #[test]
+    fn rejects_invalid_guids() {
+        assert!(parse_guid("").is_err());
+        assert!(parse_guid("{1DA5D803-D492-4EDD-8C23}").is_err());
+        assert!(parse_guid("{1DA5D803-D492-4EDD-8C23-E0C0FFEE7F0G}").is_err());
+    }
+}
@@ -0,0 +1,68 @@
+//! Audio sessions -- the per-application streams playing to or recording from a device.
+
+use windows::{
+    core::{AgileReference, Interface, Result},
+    Win32::{
+        Media::Audio::{
+            AudioSessionStateActive, Endpoints::IAudioMeterInformation, IAudioSessionControl2,
+            IAudioSessionManager2, IMMDevice,
+        },
+        System::Com::CLSCTX_ALL,
+    },
+};
+
+pub struct AudioSession {
+    // Agile, like `AudioDevice`'s device, so sessions can be handed between threads
+    control: AgileReference<IAudioSessionControl2>,
+}
+
+impl AudioSession {
+    pub fn is_active(&self) -> Result<bool> {
+        Ok(unsafe { self.control.resolve()?.GetState()? } == AudioSessionStateActive)
+    }
+
+    /// Peak sample value of this session alone, in the range 0.0 to 1.0
+    pub fn peak_value(&self) -> Result<f32> {
+        unsafe {
+            self.control
+                .resolve()?
+                .cast::<IAudioMeterInformation>()?
+                .GetPeakValue()
+        }
+    }
+}
+
+/// Number of sessions on `device` that are currently active
+///
+/// This only reads each session's state, without wrapping it in an `AudioSession`.
+pub fn active_session_count(device: &IMMDevice) -> Result<usize> {
+    unsafe {
+        let manager: IAudioSessionManager2 = device.Activate(CLSCTX_ALL, None)?;
+        let enumerator = manager.GetSessionEnumerator()?;
+
+        let mut count = 0;
+        for idx in 0..enumerator.GetCount()? {
+            if enumerator.GetSession(idx)?.GetState()? == AudioSessionStateActive {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+/// All the sessions (of any state) on `device`
+pub fn sessions(device: &IMMDevice) -> Result<Vec<AudioSession>> {
+    unsafe {
+        let manager: IAudioSessionManager2 = device.Activate(CLSCTX_ALL, None)?;
+        let enumerator = manager.GetSessionEnumerator()?;
+
+        (0..enumerator.GetCount()?)
+            .map(|idx| {
+                let control: IAudioSessionControl2 = enumerator.GetSession(idx)?.cast()?;
+                Ok(AudioSession {
+                    control: AgileReference::new(&control)?,
+                })
+            })
+            .collect()
+    }
+}
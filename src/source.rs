@@ -0,0 +1,198 @@
+//! The source of devices and notifications behind a `DeviceCollection`.
+
+use std::collections::HashSet;
+
+use windows::Win32::Media::Audio::{EDataFlow, ERole, IMMNotificationClient};
+
+use crate::collection::{DeviceCollection, DeviceEnumerator};
+use crate::device::{normalize_device_id, AudioDevice};
+use crate::enums;
+
+/// Everything the Python layer needs from the system's device enumerator
+///
+/// This is a trait so that the Python layer can be exercised against `FakeDeviceSource` in tests,
+/// without any audio endpoints present.
+pub trait DeviceSource: Send + Sync {
+    fn get_device(&self, device_id: &str) -> anyhow::Result<AudioDevice>;
+
+    fn get_collection(
+        &self,
+        dataflow: enums::DataFlow,
+        state_mask: enums::DeviceState,
+    ) -> anyhow::Result<DeviceCollection>;
+
+    /// Ids of the devices matching `dataflow` and `state_mask`
+    fn device_ids(
+        &self,
+        dataflow: enums::DataFlow,
+        state_mask: enums::DeviceState,
+    ) -> anyhow::Result<Vec<String>>;
+
+    fn get_default_device(&self, dataflow: EDataFlow, role: ERole) -> anyhow::Result<AudioDevice>;
+
+    fn get_default_device_id(
+        &self,
+        dataflow: EDataFlow,
+        role: ERole,
+    ) -> anyhow::Result<Option<String>>;
+
+    fn register_notification(&self, client: &IMMNotificationClient) -> anyhow::Result<()>;
+
+    fn unregister_notification(&self, client: &IMMNotificationClient) -> anyhow::Result<()>;
+
+    /// Unregister every listener created through this source
+    fn close_all(&self) -> anyhow::Result<()>;
+}
+
+impl DeviceSource for DeviceEnumerator {
+    fn get_device(&self, device_id: &str) -> anyhow::Result<AudioDevice> {
+        DeviceEnumerator::get_device(self, device_id)
+    }
+
+    fn get_collection(
+        &self,
+        dataflow: enums::DataFlow,
+        state_mask: enums::DeviceState,
+    ) -> anyhow::Result<DeviceCollection> {
+        DeviceEnumerator::get_collection(self, dataflow, state_mask)
+    }
+
+    fn device_ids(
+        &self,
+        dataflow: enums::DataFlow,
+        state_mask: enums::DeviceState,
+    ) -> anyhow::Result<Vec<String>> {
+        let devices = DeviceEnumerator::get_collection(self, dataflow, state_mask)?;
+        (0..devices.length()?)
+            .map(|idx| Ok(devices.get(idx)?.id.clone()))
+            .collect()
+    }
+
+    fn get_default_device(&self, dataflow: EDataFlow, role: ERole) -> anyhow::Result<AudioDevice> {
+        DeviceEnumerator::get_default_device(self, dataflow, role)
+    }
+
+    fn get_default_device_id(
+        &self,
+        dataflow: EDataFlow,
+        role: ERole,
+    ) -> anyhow::Result<Option<String>> {
+        DeviceEnumerator::get_default_device_id(self, dataflow, role)
+    }
+
+    fn register_notification(&self, client: &IMMNotificationClient) -> anyhow::Result<()> {
+        DeviceEnumerator::register_notification(self, client)
+    }
+
+    fn unregister_notification(&self, client: &IMMNotificationClient) -> anyhow::Result<()> {
+        DeviceEnumerator::unregister_notification(self, client)
+    }
+
+    fn close_all(&self) -> anyhow::Result<()> {
+        DeviceEnumerator::close_all(self)
+    }
+}
+
+/// The roles for which `device_id` is currently the default device of `dataflow`
+pub fn default_roles(
+    source: &dyn DeviceSource,
+    device_id: &str,
+    dataflow: enums::DataFlow,
+) -> anyhow::Result<Vec<enums::Role>> {
+    let mut roles = Vec::new();
+    for role in enums::Role::ALL {
+        if source
+            .get_default_device_id(dataflow.into(), role.into())?
+            .as_deref()
+            == Some(device_id)
+        {
+            roles.push(role);
+        }
+    }
+    Ok(roles)
+}
+
+/// Compare the devices (of any state) that exist now with the ids in `previous`
+///
+/// Returns the ids of the devices that aren't in `previous`, then the ids from `previous` (as
+/// given) that no longer exist. Ids are compared after `normalize_device_id`.
+pub fn diff_device_ids(
+    source: &dyn DeviceSource,
+    previous: &[String],
+) -> anyhow::Result<(Vec<String>, Vec<String>)> {
+    let current = source.device_ids(enums::DataFlow::All, enums::DeviceState::All)?;
+    let current_set: HashSet<String> = current.iter().map(|id| normalize_device_id(id)).collect();
+    let previous_set: HashSet<String> = previous.iter().map(|id| normalize_device_id(id)).collect();
+
+    let added = current
+        .into_iter()
+        .filter(|id| !previous_set.contains(&normalize_device_id(id)))
+        .collect();
+    let removed = previous
+        .iter()
+        .filter(|id| !current_set.contains(&normalize_device_id(id)))
+        .cloned()
+        .collect();
+    Ok((added, removed))
+}
+
+// The fake is only available on Windows, see `fake_source`
+#[cfg(all(test, windows))]
+mod tests {
+    use super::*;
+    use crate::fake_source::FakeDeviceSource;
+    use enums::{DataFlow, Role};
+
+    #[test]
+    fn roles_of_default_device() {
+        let mut source = FakeDeviceSource::sample();
+        source.defaults.push((
+            DataFlow::Render.into(),
+            Role::Multimedia.into(),
+            "speakers".into(),
+        ));
+        source.defaults.push((
+            DataFlow::Render.into(),
+            Role::Communications.into(),
+            "headset".into(),
+        ));
+
+        assert_eq!(
+            default_roles(&source, "speakers", DataFlow::Render).unwrap(),
+            [Role::Console, Role::Multimedia]
+        );
+        assert_eq!(
+            default_roles(&source, "mic", DataFlow::Capture).unwrap(),
+            []
+        );
+    }
+
+    #[test]
+    fn diff_against_previous_ids() {
+        let source = FakeDeviceSource::sample();
+        let previous = vec!["SPEAKERS".to_string(), "webcam".to_string()];
+
+        let (added, removed) = diff_device_ids(&source, &previous).unwrap();
+        assert_eq!(added, ["headset", "mic"]);
+        assert_eq!(removed, ["webcam"]);
+    }
+
+    #[test]
+    fn fake_default_ids() {
+        let source: &dyn DeviceSource = &FakeDeviceSource::sample();
+
+        assert_eq!(
+            source
+                .get_default_device_id(DataFlow::Render.into(), Role::Console.into())
+                .unwrap()
+                .as_deref(),
+            Some("speakers")
+        );
+        assert_eq!(
+            source
+                .get_default_device_id(DataFlow::Capture.into(), Role::Console.into())
+                .unwrap(),
+            None
+        );
+    }
+}
@@ -1,4 +1,6 @@
 import asyncio
+import json
+from array import array
 import os
 import sys
 import pytest
@@ -11,6 +13,9 @@ from windows_audio_control import (
     DeviceState,
     DataFlow,
     Role,
+    DuckingPreference,
+    normalize_device_id,
+    LIBRARY_CONTEXT,
 )
 
 
@@ -19,6 +24,23 @@ def collection():
     return DeviceCollection()
 
 
+@pytest.fixture
+def restore_defaults(collection: DeviceCollection):
+    """Put back the default device of every data flow and role once the test is done"""
+    saved = []
+    for dataflow in (DataFlow.RENDER, DataFlow.CAPTURE):
+        for role in (Role.CONSOLE, Role.MULTIMEDIA, Role.COMMS):
+            try:
+                saved.append((collection.get_default_device(dataflow, role), role))
+            except KeyError:
+                pass
+    try:
+        yield
+    finally:
+        for device, role in saved:
+            device.set_default(role)
+
+
 def test_device_not_found(collection: DeviceCollection):
     with pytest.raises(KeyError):
         collection.devices["I am not a valid device ID"]
@@ -88,3 +110,727 @@ async def test_make_default(collection: DeviceCollection):
             break
     finally:
         current.set_default(Role.MULTIMEDIA)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '2')) <= 1, reason="Test needs multiple audio devices")
+async def test_matches_event(collection: DeviceCollection, restore_defaults):
+    playback_devices = collection.filter_devices(DataFlow.RENDER, DeviceState.ACTIVE)
+    current = collection.get_default_output_device()
+    other = next(dev for dev in playback_devices if dev != current)
+
+    async def next_default_change():
+        async for event in collection.events:
+            if event.kind == DeviceCollectionEventType.DEFAULT_CHANGED and event.role == Role.MULTIMEDIA:
+                return event
+
+    task = asyncio.create_task(next_default_change())
+    await asyncio.sleep(0)
+    other.set_default(Role.MULTIMEDIA)
+    event = await asyncio.wait_for(task, timeout=5)
+
+    assert other.matches_event(event)
+    assert not current.matches_event(event)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_physical_speakers(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    speakers = playback.physical_speakers
+    assert isinstance(speakers, list)
+    assert all(isinstance(position, str) for position in speakers)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_device_token(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    token = playback.to_token()
+    assert token["data_flow"] == "RENDER"
+
+    assert collection.from_token(token) == playback
+    # An unknown id falls back to matching by name
+    assert collection.from_token({**token, "id": "I am not a valid device ID"}).name == playback.name
+
+    with pytest.raises(KeyError):
+        collection.from_token({"id": "I am not a valid device ID", "name": "I am not a valid device name"})
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_devices_in_container(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    if playback.container_id is None:
+        pytest.skip("Default output device has no container id")
+
+    assert playback in collection.devices_in_container(playback.container_id)
+
+
+async def test_events_closed(collection: DeviceCollection):
+    events = collection.events
+    assert not events.closed
+
+    events.close()
+    assert events.closed
+
+    async for _ in events:
+        pytest.fail("Closed iterator yielded an event")
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_devices_by_flow(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+
+    assert collection.output_devices[playback.device_id] == playback
+    assert playback.device_id in collection.output_devices.keys()
+    assert playback.device_id not in collection.input_devices.keys()
+    with pytest.raises(KeyError):
+        collection.input_devices[playback.device_id]
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+@pytest.mark.parametrize("role", [Role.CONSOLE, Role.COMMS, Role.MULTIMEDIA], ids=lambda role: repr(role))
+def test_default_device_for_role(role, collection: DeviceCollection):
+    playback = collection.get_default_device(DataFlow.RENDER, role)
+    assert isinstance(playback, AudioDevice)
+    assert playback == collection.get_default_device(DataFlow.RENDER, role)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+async def test_volume_events_emit_initial(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    events = playback.volume_events(emit_initial=True)
+
+    event = await asyncio.wait_for(events.__anext__(), timeout=1)
+    assert event.device == playback
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_all_devices(collection: DeviceCollection):
+    devices = collection.all_devices(DeviceState.ACTIVE)
+
+    assert collection.get_default_output_device() in devices
+    assert len(devices) == len(collection.filter_devices(DataFlow.ALL, DeviceState.ACTIVE))
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_current_default_token(collection: DeviceCollection):
+    token = collection.current_default_token(DataFlow.RENDER, Role.CONSOLE)
+
+    assert token is not None
+    assert token == collection.current_default_token(DataFlow.RENDER, Role.CONSOLE)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+async def test_mute_for_restores_on_cancel(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    playback.set_mute(False)
+
+    task = asyncio.ensure_future(playback.mute_for(60_000))
+    await asyncio.sleep(0.1)
+    task.cancel()
+    with pytest.raises(asyncio.CancelledError):
+        await task
+
+    event = await asyncio.wait_for(playback.volume_events(emit_initial=True).__anext__(), timeout=1)
+    assert not event.mute
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_device_equals_id(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+
+    assert playback == playback.device_id
+    assert playback != "not-a-device-id"
+    assert playback != 1
+
+
+def test_ducking_preference(collection: DeviceCollection):
+    assert isinstance(collection.ducking_preference, DuckingPreference)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_devices_ordered(collection: DeviceCollection):
+    devices = collection.devices_ordered(DataFlow.RENDER)
+
+    assert devices[0] == collection.get_default_output_device()
+    names = [device.name for device in devices[1:]]
+    assert names == sorted(names)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+async def test_device_events_stop(collection: DeviceCollection):
+    events = collection.get_default_output_device().events
+    events.stop()
+
+    async for _ in events:
+        pytest.fail("Stopped iterator yielded an event")
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_default_summary(collection: DeviceCollection):
+    summary = collection.default_summary()
+
+    assert summary.output.device == collection.get_default_output_device()
+    assert 0.0 <= summary.output.volume <= 1.0
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_mix_format(collection: DeviceCollection):
+    mix = collection.get_default_output_device().mix_format()
+
+    assert mix.sample_format in ("pcm", "float")
+    assert mix.valid_bits_per_sample <= mix.bits_per_sample
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_mix_format_to_float32(collection: DeviceCollection):
+    mix = collection.get_default_output_device().mix_format()
+    silence = bytes(mix.bits_per_sample // 8 * mix.channels)
+
+    assert array('f', mix.to_float32(silence)).tolist() == [0.0] * mix.channels
+    with pytest.raises(ValueError):
+        mix.to_float32(silence[:-1])
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_session_device(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+
+    for session in playback.sessions():
+        assert session.device == playback
+        assert session.device_id == playback.device_id
+        assert collection.device_for_session(session) == playback
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_active_session_count(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+
+    assert playback.active_session_count() == sum(session.is_active() for session in playback.sessions())
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+async def test_close_all_ends_iterators():
+    with DeviceCollection() as collection:
+        events = collection.events
+        volume_events = collection.get_default_output_device().events
+        property_events = collection.get_default_output_device().property_events
+        state_events = collection.get_default_output_device().state_events()
+
+    with pytest.raises(StopAsyncIteration):
+        await asyncio.wait_for(events.__anext__(), timeout=1)
+    with pytest.raises(StopAsyncIteration):
+        await asyncio.wait_for(volume_events.__anext__(), timeout=1)
+    with pytest.raises(StopAsyncIteration):
+        await asyncio.wait_for(property_events.__anext__(), timeout=1)
+    with pytest.raises(StopAsyncIteration):
+        await asyncio.wait_for(state_events.__anext__(), timeout=1)
+    events.close()
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+async def test_default_output_volume_events(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    events = collection.default_output_volume_events()
+
+    event = await asyncio.wait_for(events.__anext__(), timeout=1)
+    assert event.device == playback
+    events.close()
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_output_volume_snapshot(collection: DeviceCollection):
+    snapshot = collection.output_volume_snapshot()
+
+    volume, muted = snapshot[collection.get_default_output_device().device_id]
+    assert 0.0 <= volume <= 1.0
+    assert isinstance(muted, bool)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+async def test_wait_for_volume_times_out(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+
+    assert await playback.wait_for_volume(lambda event: False, timeout_ms=50) is None
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+async def test_wait_for_volume_keeps_other_listeners(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    events = playback.volume_events()
+    muted = playback.volume().get_mute()
+
+    assert await playback.wait_for_volume(lambda event: False, timeout_ms=50) is None
+
+    try:
+        playback.set_mute(not muted)
+        event = await asyncio.wait_for(events.__anext__(), timeout=5)
+        assert event.mute == (not muted)
+    finally:
+        playback.set_mute(muted)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_default_output_step(collection: DeviceCollection):
+    collection.default_output_step_down()
+    collection.default_output_step_up()
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_supports_metering(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+
+    if playback.supports_metering():
+        assert 0.0 <= playback.open_meter().peak_value() <= 1.0
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_default_roles(collection: DeviceCollection):
+    playback = collection.get_default_device(DataFlow.RENDER, Role.CONSOLE)
+
+    assert Role.CONSOLE in playback.default_roles()
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_default_output_channels(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+
+    assert collection.default_output_channels() == (playback.channel_count, playback.physical_speakers)
+
+
+def test_default_device_rejects_all(collection: DeviceCollection):
+    with pytest.raises(ValueError):
+        collection.get_default_device(DataFlow.ALL, Role.CONSOLE)
+    with pytest.raises(ValueError):
+        collection.current_default_token(DataFlow.ALL, Role.CONSOLE)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_endpoint_volume(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    volume = playback.volume()
+
+    min_db, max_db, step_db = volume.range()
+    assert min_db <= max_db
+    assert volume.channel_count() == playback.channel_count
+
+    level = volume.get()
+    volume.set(level)
+    assert volume.get() == pytest.approx(level)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_devices_capturing(collection: DeviceCollection):
+    for device in collection.devices_capturing():
+        assert device.data_flow == DataFlow.CAPTURE
+        assert device.active_session_count() > 0
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_lookup_normalizes_device_id(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    pasted = f"  {playback.device_id.upper()}\n"
+
+    assert normalize_device_id(pasted) == playback.device_id
+    assert collection.devices[pasted] == playback
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '2')) <= 1, reason="Test needs multiple audio devices")
+async def test_default_change_events(collection: DeviceCollection):
+    playback_devices = collection.filter_devices(DataFlow.RENDER, DeviceState.ACTIVE)
+    current = collection.get_default_output_device()
+    other = next(dev for dev in playback_devices if dev != current)
+
+    events = collection.default_change_events(DataFlow.RENDER, Role.MULTIMEDIA)
+    try:
+        next_default = asyncio.ensure_future(events.__anext__())
+        await asyncio.sleep(0)
+        other.set_default(Role.MULTIMEDIA)
+
+        assert await asyncio.wait_for(next_default, timeout=5) == other
+    finally:
+        events.close()
+        current.set_default(Role.MULTIMEDIA)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_is_input_is_output(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    assert playback.is_output()
+    assert not playback.is_input()
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_set_defaults_by_name(collection: DeviceCollection, restore_defaults):
+    playback = collection.get_default_output_device()
+
+    with pytest.raises(KeyError):
+        collection.set_defaults_by_name(output_name="I am not a valid device name")
+    assert collection.get_default_output_device() == playback
+
+    named = [dev for dev in collection.all_devices(DeviceState.ACTIVE) if dev.name == playback.name]
+    if len(named) > 1:
+        pytest.skip("Default output device's name is not unique")
+    assert collection.get_device_by_name(playback.name) == playback
+    collection.set_defaults_by_name(output_name=playback.name)
+    assert collection.get_default_output_device() == playback
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_list_devices_json(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    listing = collection.list_devices_json(DataFlow.RENDER)
+
+    entry = next(entry for entry in listing if entry["id"] == playback.device_id)
+    assert entry["name"] == playback.name
+    assert entry["state"] == "ACTIVE"
+    assert entry["data_flow"] == "RENDER"
+    assert entry["default_console"] is True
+    json.dumps(listing)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+async def test_with_volume_restores(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    before = playback.volume().get()
+    level = 0.25 if abs(before - 0.25) > 0.01 else 0.5
+
+    with pytest.raises(RuntimeError, match="inside"):
+        async with playback.with_volume(level) as device:
+            assert device == playback
+            assert playback.volume().get() == pytest.approx(level, abs=0.01)
+            raise RuntimeError("inside")
+
+    assert playback.volume().get() == pytest.approx(before, abs=0.01)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_enhancements_enabled(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    assert isinstance(playback.enhancements_enabled, bool)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+async def test_volume_events_master_only(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    events = playback.volume_events(emit_initial=True, master_only=True)
+
+    event = await asyncio.wait_for(events.__anext__(), timeout=1)
+    assert event.channel_volumes == ()
+    assert event.volume == pytest.approx(playback.volume().get())
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+async def test_mute_schedule_restores(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    before = playback.volume().get_mute()
+
+    outcome = collection.mute_schedule([playback.device_id, "I am not a valid device ID"], 200)
+    assert [(device_id, ok) for device_id, ok, _ in outcome] == [
+        (playback.device_id, True),
+        ("I am not a valid device ID", False),
+    ]
+    assert playback.volume().get_mute()
+
+    await asyncio.sleep(1)
+    assert playback.volume().get_mute() == before
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+async def test_mix_format_events_quiet_without_change(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    events = playback.mix_format_events(interval_ms=10)
+
+    with pytest.raises(asyncio.TimeoutError):
+        await asyncio.wait_for(events.__anext__(), timeout=0.2)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_volume_limits(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    volume = playback.volume()
+    before = volume.get()
+
+    with pytest.raises(ValueError):
+        playback.set_volume_limits(0.6, 0.2)
+
+    playback.set_volume_limits(0.2, 0.6)
+    try:
+        assert playback.volume_limits == pytest.approx((0.2, 0.6))
+        limited = playback.volume()
+        limited.set(1.0)
+        assert limited.get() == pytest.approx(0.6, abs=0.01)
+        limited.set(0.0)
+        assert limited.get() == pytest.approx(0.2, abs=0.01)
+    finally:
+        playback.clear_volume_limits()
+        volume.set(before)
+
+    assert playback.volume_limits is None
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_loudest_session(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    loudest = playback.loudest_session()
+
+    if playback.active_session_count() == 0:
+        assert loudest is None
+    elif loudest is not None:
+        assert loudest.device == playback
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+async def test_set_mute_context(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    before = playback.volume().get_mute()
+    context = "{1DA5D803-D492-4EDD-8C23-E0C0FFEE7F0E}"
+
+    with pytest.raises(ValueError):
+        playback.set_mute(before, context="not a guid")
+
+    events = playback.volume_events()
+    try:
+        playback.set_mute(not before, context=context)
+        event = await asyncio.wait_for(events.__anext__(), timeout=1)
+        assert event.context == context
+    finally:
+        playback.set_mute(before)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_diff(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    ids = list(collection.devices.keys())
+
+    assert collection.diff(ids) == ([], [])
+
+    ids.remove(playback.device_id)
+    added, removed = collection.diff(ids + ["I am not a valid device ID"])
+    assert added == [playback]
+    assert removed == ["I am not a valid device ID"]
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+async def test_library_context(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    before = playback.volume().get_mute()
+
+    events = playback.volume_events()
+    try:
+        playback.volume().set_mute(not before)
+        event = await asyncio.wait_for(events.__anext__(), timeout=1)
+        assert event.context == LIBRARY_CONTEXT
+    finally:
+        playback.set_mute(before)
+
+
+def test_problem_devices(collection: DeviceCollection):
+    for device, state in collection.problem_devices():
+        assert isinstance(device, AudioDevice)
+        assert state != DeviceState.ACTIVE
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_level_poller(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    volume, mute, peak = playback.level_poller().poll()
+
+    assert volume == pytest.approx(playback.volume().get())
+    assert mute == playback.volume().get_mute()
+    assert 0.0 <= peak <= 1.0
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_exclusive_mode_properties(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    assert playback.exclusive_mode_allowed in (True, False, None)
+    assert playback.supports_event_driven_mode in (True, False, None)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_snapshot_state(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    state = collection.snapshot_state()
+
+    assert state.output.device == playback
+    assert state.active_outputs >= 1
+
+    snapshot = state.to_dict()
+    assert snapshot["output"]["device_id"] == playback.device_id
+    assert snapshot["active_outputs"] == state.active_outputs
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+async def test_volume_events_with_db(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    plain = await asyncio.wait_for(playback.volume_events(emit_initial=True).__anext__(), timeout=1)
+    event = await asyncio.wait_for(playback.volume_events(emit_initial=True, with_db=True).__anext__(), timeout=1)
+
+    assert plain.volume_db is None
+    assert event.volume == pytest.approx(plain.volume)
+    assert isinstance(event.volume_db, float)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+async def test_volume_events_in_db(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    scalar = await asyncio.wait_for(playback.volume_events(emit_initial=True, with_db=True).__anext__(), timeout=1)
+    event = await asyncio.wait_for(
+        playback.volume_events(emit_initial=True, unit="db").__anext__(), timeout=1
+    )
+
+    assert event.volume == pytest.approx(scalar.volume_db)
+    assert event.volume_db == pytest.approx(scalar.volume_db)
+
+    with pytest.raises(ValueError):
+        playback.volume_events(unit="percent")
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+async def test_volume_events_min_interval(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    events = playback.volume_events(emit_initial=True, min_interval_ms=50)
+
+    event = await asyncio.wait_for(events.__anext__(), timeout=1)
+    assert event.device == playback
+    events.stop()
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_get_devices_by_name(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    found = collection.get_devices_by_name(playback.name)
+
+    assert playback in found
+    assert [dev.device_id for dev in found] == sorted(dev.device_id for dev in found)
+    assert collection.get_devices_by_name("no device has this name") == []
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+async def test_state_events_closes(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    events = playback.state_events()
+    events.close()
+
+    with pytest.raises(StopAsyncIteration):
+        await events.__anext__()
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_endpoint_guid(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+
+    if playback.endpoint_guid is not None:
+        assert playback.endpoint_guid.startswith("{") and playback.endpoint_guid.endswith("}")
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_toggle_container_mute(collection: DeviceCollection):
+    playback = collection.get_default_output_device()
+    if playback.container_id is None:
+        pytest.skip("default output device has no container id")
+
+    devices = collection.devices_in_container(playback.container_id)
+    before = [(device, device.volume().get_mute()) for device in devices]
+    was_muted = playback.volume().get_mute()
+    try:
+        assert collection.toggle_container_mute(playback.container_id) == (not was_muted)
+        for device in devices:
+            assert device.volume().get_mute() == (not was_muted)
+    finally:
+        for device, muted in before:
+            device.set_mute(muted)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '2')) <= 1, reason="Test needs multiple audio devices")
+async def test_watch_stop(collection: DeviceCollection, restore_defaults):
+    playback_devices = collection.filter_devices(DataFlow.RENDER, DeviceState.ACTIVE)
+    current = collection.get_default_output_device()
+    other = next(dev for dev in playback_devices if dev != current)
+
+    events = []
+    errors = []
+    watcher = collection.watch(events.append, on_error=errors.append)
+    watcher.stop()
+    # Stopping twice is harmless
+    watcher.stop()
+
+    other.set_default(Role.MULTIMEDIA)
+    current.set_default(Role.MULTIMEDIA)
+    await asyncio.sleep(1)
+
+    assert events == []
+    assert errors == []
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '2')) <= 1, reason="Test needs multiple audio devices")
+async def test_watch_survives_on_event_raising(collection: DeviceCollection, restore_defaults):
+    playback_devices = collection.filter_devices(DataFlow.RENDER, DeviceState.ACTIVE)
+    current = collection.get_default_output_device()
+    other = next(dev for dev in playback_devices if dev != current)
+
+    errors = []
+
+    def on_event(event):
+        raise ValueError("handler failed")
+
+    watcher = collection.watch(on_event, on_error=errors.append)
+    try:
+        other.set_default(Role.MULTIMEDIA)
+        current.set_default(Role.MULTIMEDIA)
+        for _ in range(50):
+            if len(errors) >= 2:
+                break
+            await asyncio.sleep(0.1)
+    finally:
+        watcher.stop()
+
+    # Both changes of default are delivered, even though handling the first one raised
+    assert len(errors) >= 2
+    assert all(isinstance(err, ValueError) for err in errors)
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_mute_all(collection: DeviceCollection):
+    devices = collection.all_devices(DeviceState.ACTIVE)
+    outputs = {dev.device_id: dev.volume().get_mute() for dev in devices if dev.is_output()}
+    try:
+        outcome = collection.mute_all(DataFlow.RENDER, True)
+        assert {device_id for device_id, _, _ in outcome} == set(outputs)
+        for device_id, ok, error_message in outcome:
+            assert ok == (error_message is None)
+    finally:
+        for dev in devices:
+            if dev.device_id in outputs:
+                dev.set_mute(outputs[dev.device_id])
+
+
+def test_mute_all_inputs(collection: DeviceCollection):
+    devices = collection.all_devices(DeviceState.ACTIVE)
+    inputs = {dev.device_id: dev.volume().get_mute() for dev in devices if dev.is_input()}
+    try:
+        outcome = collection.mute_all_inputs()
+        assert {device_id for device_id, _, _ in outcome} == set(inputs)
+        for device_id, ok, error_message in outcome:
+            assert ok == (error_message is None)
+    finally:
+        for dev in devices:
+            if dev.device_id in inputs:
+                dev.set_mute(inputs[dev.device_id])
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_set_default_all_roles(collection: DeviceCollection, restore_defaults):
+    playback = collection.get_default_output_device()
+
+    outcome = playback.set_default_all_roles()
+    assert [(role, ok) for role, ok, _ in outcome] == [("CONSOLE", True), ("MULTIMEDIA", True), ("COMMS", True)]
+    assert collection.get_default_device(DataFlow.RENDER, Role.COMMS) == playback
+
+
+@pytest.mark.skipif(int(os.environ.get('NUM_AUDIO_DEVICES', '1')) <= 0, reason="No audio devices found")
+def test_set_default_devices(collection: DeviceCollection, restore_defaults):
+    playback = collection.get_default_output_device()
+
+    outcome = collection.set_default_devices(output_id=playback.device_id, input_id="not-a-device-id")
+    assert [ok for _, ok, _ in outcome] == [True] * 3 + [False] * 3
+    assert outcome[0][0] == f"{playback.device_id} CONSOLE"